@@ -0,0 +1,455 @@
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use std::num::{NonZeroU32, NonZeroU64};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, OnceLock, RwLock};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use chombo_gen_common::api_keys::{ApiKeyUsageResponse, ApiKeyUsageStats};
+use cot::http::HeaderName;
+use cot::json::Json;
+use cot::request::Request;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use time::OffsetDateTime;
+use time::format_description::well_known::Rfc3339;
+use tracing::{error, info};
+use uuid::Uuid;
+
+use crate::errors::ServiceError;
+
+pub const API_KEYS_FILE_ENV: &str = "CHOMBO_GEN_API_KEYS_FILE";
+pub const API_KEY_HEADER: HeaderName = HeaderName::from_static("x-api-key");
+const API_KEY_PREFIX: &str = "cg_";
+/// How often the key file is checked for changes, so that keys created or
+/// revoked with the CLI take effect without restarting the server.
+const RELOAD_INTERVAL: Duration = Duration::from_secs(10);
+/// How often the usage counters are written to disk.
+const USAGE_FLUSH_INTERVAL: Duration = Duration::from_secs(60);
+const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+
+static API_KEY_STORE: OnceLock<Option<Arc<ApiKeyStore>>> = OnceLock::new();
+
+/// Opens the key file configured with the environment variable, if any.
+/// Called when the project config is loaded, so that an invalid key file is
+/// reported at startup.
+pub fn init_api_key_store() -> Result<(), ApiKeyError> {
+    if API_KEY_STORE.get().is_none() {
+        let store = ApiKeyStore::from_env()?.map(Arc::new);
+        API_KEY_STORE.get_or_init(|| store);
+    }
+    Ok(())
+}
+
+/// Returns the store opened with [`init_api_key_store`], or `None` if API
+/// keys are disabled.
+#[must_use]
+pub fn api_key_store() -> Option<Arc<ApiKeyStore>> {
+    API_KEY_STORE
+        .get()
+        .expect("API keys are loaded with the project config")
+        .clone()
+}
+
+#[derive(Debug)]
+pub enum ApiKeyError {
+    Io(PathBuf, std::io::Error),
+    Parse(PathBuf, toml::de::Error),
+    Serialize(PathBuf, toml::ser::Error),
+    DuplicateName(String),
+    InvalidName(String),
+    NotFound(String),
+}
+
+impl Display for ApiKeyError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Io(path, error) => {
+                write!(f, "could not access {}: {error}", path.display())
+            }
+            Self::Parse(path, error) => {
+                write!(f, "invalid API key file {}: {error}", path.display())
+            }
+            Self::Serialize(path, error) => {
+                write!(f, "could not serialize {}: {error}", path.display())
+            }
+            Self::DuplicateName(name) => {
+                write!(f, "API key `{name}` already exists")
+            }
+            Self::InvalidName(name) => {
+                write!(
+                    f,
+                    "invalid API key name `{name}`, only letters, digits, `-` and `_` are allowed"
+                )
+            }
+            Self::NotFound(name) => {
+                write!(f, "API key `{name}` does not exist")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ApiKeyError {}
+
+/// An API key, as stored in the key file. The key itself is only shown when
+/// it's created; the file contains its hash.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ApiKey {
+    pub name: String,
+    /// SHA-256 of the key, in hex.
+    pub key_hash: String,
+    pub requests_per_minute: NonZeroU32,
+    pub burst: NonZeroU32,
+    /// Maximum number of requests per UTC day, or unlimited if `None`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub daily_quota: Option<NonZeroU64>,
+    pub created_at: String,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct ApiKeysFile {
+    #[serde(default, rename = "key")]
+    keys: Vec<ApiKey>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ApiKeyUsage {
+    pub total_requests: u64,
+    /// Day (since the Unix epoch, in UTC) `requests_today` refers to.
+    pub day: u64,
+    pub requests_today: u64,
+}
+
+impl ApiKeyUsage {
+    /// Returns the number of requests made today, which is 0 if the counters
+    /// were last updated on a previous day.
+    #[must_use]
+    pub fn requests_on_current_day(&self) -> u64 {
+        if self.day == current_day() {
+            self.requests_today
+        } else {
+            0
+        }
+    }
+
+    fn roll_over(&mut self, today: u64) {
+        if self.day != today {
+            self.day = today;
+            self.requests_today = 0;
+        }
+    }
+}
+
+/// Returns the file the usage counters of the keys in `keys_file` are
+/// stored in.
+#[must_use]
+pub fn usage_file_path(keys_file: &Path) -> PathBuf {
+    keys_file.with_extension("usage.toml")
+}
+
+fn read_toml_file<T>(path: &Path) -> Result<T, ApiKeyError>
+where
+    T: Default + for<'de> Deserialize<'de>,
+{
+    match std::fs::read_to_string(path) {
+        Ok(contents) => {
+            toml::from_str(&contents).map_err(|error| ApiKeyError::Parse(path.to_owned(), error))
+        }
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => Ok(T::default()),
+        Err(error) => Err(ApiKeyError::Io(path.to_owned(), error)),
+    }
+}
+
+/// Replaces the contents of a file atomically, so that the server never reads
+/// a partially written file.
+fn write_toml_file<T: Serialize>(path: &Path, value: &T) -> Result<(), ApiKeyError> {
+    let contents =
+        toml::to_string(value).map_err(|error| ApiKeyError::Serialize(path.to_owned(), error))?;
+    let temp_path = path.with_extension("tmp");
+    std::fs::write(&temp_path, contents)
+        .and_then(|()| std::fs::rename(&temp_path, path))
+        .map_err(|error| ApiKeyError::Io(path.to_owned(), error))
+}
+
+pub fn read_api_keys(path: &Path) -> Result<Vec<ApiKey>, ApiKeyError> {
+    read_toml_file::<ApiKeysFile>(path).map(|file| file.keys)
+}
+
+pub fn read_api_key_usage(keys_file: &Path) -> Result<HashMap<String, ApiKeyUsage>, ApiKeyError> {
+    read_toml_file(&usage_file_path(keys_file))
+}
+
+fn hash_api_key(key: &str) -> String {
+    Sha256::digest(key.as_bytes())
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
+
+/// Limits of a newly created API key.
+#[derive(Debug, Clone, Copy)]
+pub struct ApiKeyLimits {
+    pub requests_per_minute: NonZeroU32,
+    pub burst: NonZeroU32,
+    pub daily_quota: Option<NonZeroU64>,
+}
+
+/// Adds a new key to the key file, returning the key's secret.
+pub fn create_api_key(
+    path: &Path,
+    name: &str,
+    limits: ApiKeyLimits,
+) -> Result<String, ApiKeyError> {
+    if name.is_empty()
+        || !name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        return Err(ApiKeyError::InvalidName(name.to_string()));
+    }
+
+    let mut file = read_toml_file::<ApiKeysFile>(path)?;
+    if file.keys.iter().any(|key| key.name == name) {
+        return Err(ApiKeyError::DuplicateName(name.to_string()));
+    }
+
+    let secret = format!("{API_KEY_PREFIX}{}", Uuid::new_v4().simple());
+    file.keys.push(ApiKey {
+        name: name.to_string(),
+        key_hash: hash_api_key(&secret),
+        requests_per_minute: limits.requests_per_minute,
+        burst: limits.burst,
+        daily_quota: limits.daily_quota,
+        created_at: OffsetDateTime::now_utc()
+            .replace_nanosecond(0)
+            .expect("0 is a valid nanosecond")
+            .format(&Rfc3339)
+            .expect("current time is always formattable"),
+    });
+    write_toml_file(path, &file)?;
+
+    Ok(secret)
+}
+
+/// Removes a key from the key file.
+pub fn revoke_api_key(path: &Path, name: &str) -> Result<(), ApiKeyError> {
+    let mut file = read_toml_file::<ApiKeysFile>(path)?;
+    let key_count = file.keys.len();
+    file.keys.retain(|key| key.name != name);
+    if file.keys.len() == key_count {
+        return Err(ApiKeyError::NotFound(name.to_string()));
+    }
+
+    write_toml_file(path, &file)
+}
+
+fn key_file_modified(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
+
+fn current_day() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |since_epoch| since_epoch.as_secs() / SECONDS_PER_DAY)
+}
+
+fn seconds_until_next_day() -> u64 {
+    let since_epoch = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |since_epoch| since_epoch.as_secs());
+    SECONDS_PER_DAY - since_epoch % SECONDS_PER_DAY
+}
+
+/// Keys by the hash of their secret.
+type KeysByHash = HashMap<String, Arc<ApiKey>>;
+
+#[derive(Debug)]
+struct UsageCounters {
+    by_name: HashMap<String, ApiKeyUsage>,
+    dirty: bool,
+    flushed_at: Instant,
+}
+
+/// Outcome of counting a request made with an API key.
+#[derive(Debug, Clone, Copy)]
+pub enum QuotaState {
+    Allowed(ApiKeyUsage),
+    /// The daily quota is used up; contains the number of seconds until it's
+    /// reset.
+    Exceeded(u64),
+}
+
+/// API keys loaded from the key file, along with their usage counters.
+#[derive(Debug)]
+pub struct ApiKeyStore {
+    path: PathBuf,
+    keys: RwLock<Arc<KeysByHash>>,
+    /// Modification time of the key file when it was last loaded; only used
+    /// by the reloading task.
+    modified: Mutex<Option<SystemTime>>,
+    usage: Arc<Mutex<UsageCounters>>,
+}
+
+impl ApiKeyStore {
+    /// Opens the key file configured with the environment variable; returns
+    /// `None` if API keys are disabled.
+    pub fn from_env() -> Result<Option<Self>, ApiKeyError> {
+        std::env::var_os(API_KEYS_FILE_ENV)
+            .map(|path| Self::open(path.into()))
+            .transpose()
+    }
+
+    pub fn open(path: PathBuf) -> Result<Self, ApiKeyError> {
+        let modified = key_file_modified(&path);
+        let keys = Self::load_keys(&path)?;
+        let usage = read_api_key_usage(&path)?;
+        info!("Loaded {} API keys from {}", keys.len(), path.display());
+
+        Ok(Self {
+            path,
+            keys: RwLock::new(Arc::new(keys)),
+            modified: Mutex::new(modified),
+            usage: Arc::new(Mutex::new(UsageCounters {
+                by_name: usage,
+                dirty: false,
+                flushed_at: Instant::now(),
+            })),
+        })
+    }
+
+    fn load_keys(path: &Path) -> Result<KeysByHash, ApiKeyError> {
+        Ok(read_api_keys(path)?
+            .into_iter()
+            .map(|key| (key.key_hash.clone(), Arc::new(key)))
+            .collect())
+    }
+
+    /// Starts checking the key file for changes in the background, every
+    /// [`RELOAD_INTERVAL`]. The usage counters are saved along the way, so
+    /// that they are not only saved when requests are made.
+    pub fn start_reloading(self: &Arc<Self>) {
+        let store = Arc::downgrade(self);
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(RELOAD_INTERVAL);
+            interval.tick().await;
+            loop {
+                interval.tick().await;
+                let Some(store) = store.upgrade() else {
+                    return;
+                };
+                let reloaded_store = Arc::clone(&store);
+                if let Err(error) =
+                    tokio::task::spawn_blocking(move || reloaded_store.reload_if_changed()).await
+                {
+                    error!("Could not reload API keys: {error}");
+                }
+                store.flush_usage_if_due();
+            }
+        });
+    }
+
+    fn reload_if_changed(&self) {
+        let mut modified = self.modified.lock().expect("API key lock poisoned");
+        let current_modified = key_file_modified(&self.path);
+        if current_modified == *modified {
+            return;
+        }
+        match Self::load_keys(&self.path) {
+            Ok(loaded) => {
+                info!("Reloaded {} API keys", loaded.len());
+                *self.keys.write().expect("API key lock poisoned") = Arc::new(loaded);
+                *modified = current_modified;
+            }
+            // Keep the previous keys rather than locking everyone out
+            Err(error) => error!("Could not reload API keys: {error}"),
+        }
+    }
+
+    /// Returns the key with the given secret, if it exists.
+    pub fn authenticate(&self, secret: &str) -> Option<Arc<ApiKey>> {
+        let keys = Arc::clone(&self.keys.read().expect("API key lock poisoned"));
+        keys.get(&hash_api_key(secret)).cloned()
+    }
+
+    /// Counts a request made with `key`, unless its daily quota is used up.
+    pub fn record_request(&self, key: &ApiKey) -> QuotaState {
+        let mut usage = self.usage.lock().expect("API key usage lock poisoned");
+        let counters = usage.by_name.entry(key.name.clone()).or_default();
+        counters.roll_over(current_day());
+        let state = if key
+            .daily_quota
+            .is_some_and(|quota| counters.requests_today >= quota.get())
+        {
+            QuotaState::Exceeded(seconds_until_next_day())
+        } else {
+            counters.total_requests += 1;
+            counters.requests_today += 1;
+            QuotaState::Allowed(*counters)
+        };
+        // Also set when the quota is exceeded, as the day may have rolled over
+        usage.dirty = true;
+        drop(usage);
+
+        self.flush_usage_if_due();
+        state
+    }
+
+    /// Writes the usage counters to disk in the background if they haven't
+    /// been written for [`USAGE_FLUSH_INTERVAL`].
+    fn flush_usage_if_due(&self) {
+        let mut usage = self.usage.lock().expect("API key usage lock poisoned");
+        if usage.flushed_at.elapsed() >= USAGE_FLUSH_INTERVAL {
+            usage.flushed_at = Instant::now();
+            self.flush_usage();
+        }
+    }
+
+    /// Writes the usage counters to disk in the background.
+    fn flush_usage(&self) {
+        let path = usage_file_path(&self.path);
+        let usage = Arc::clone(&self.usage);
+        tokio::task::spawn_blocking(move || {
+            let snapshot = {
+                let mut usage = usage.lock().expect("API key usage lock poisoned");
+                if !usage.dirty {
+                    return;
+                }
+                usage.dirty = false;
+                usage.by_name.clone()
+            };
+            if let Err(error) = write_toml_file(&path, &snapshot) {
+                error!("Could not save API key usage: {error}");
+            }
+        });
+    }
+}
+
+/// The API key a request was made with, added to the request extensions by
+/// [`RateLimitMiddleware`](crate::rate_limit::RateLimitMiddleware).
+#[derive(Debug, Clone)]
+pub struct AuthenticatedApiKey {
+    pub key: Arc<ApiKey>,
+    pub usage: ApiKeyUsage,
+}
+
+/// Returns the limits and usage of the API key the request was made with.
+pub async fn api_key_usage(request: Request) -> Result<Json<ApiKeyUsageResponse>, ServiceError> {
+    let Some(api_key) = request.extensions().get::<AuthenticatedApiKey>() else {
+        return Err(ServiceError::Unauthorized(
+            "This endpoint requires an API key in the X-API-Key header".to_string(),
+        ));
+    };
+
+    Ok(Json(ApiKeyUsageResponse {
+        name: api_key.key.name.clone(),
+        requests_per_minute: api_key.key.requests_per_minute.get(),
+        burst: api_key.key.burst.get(),
+        daily_quota: api_key.key.daily_quota.map(NonZeroU64::get),
+        usage: ApiKeyUsageStats {
+            total_requests: api_key.usage.total_requests,
+            requests_today: api_key.usage.requests_today,
+        },
+    }))
+}
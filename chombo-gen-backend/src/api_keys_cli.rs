@@ -0,0 +1,133 @@
+use std::num::{NonZeroU32, NonZeroU64};
+use std::path::PathBuf;
+
+use async_trait::async_trait;
+use cot::cli::CliTask;
+use cot::cli::clap::{Arg, ArgMatches, Command, value_parser};
+use cot::project::{Bootstrapper, WithConfig};
+
+use crate::api_keys::{
+    API_KEYS_FILE_ENV, ApiKeyLimits, create_api_key, read_api_key_usage, read_api_keys,
+    revoke_api_key,
+};
+
+const FILE_PARAM: &str = "file";
+const NAME_PARAM: &str = "name";
+const REQUESTS_PER_MINUTE_PARAM: &str = "requests-per-minute";
+const BURST_PARAM: &str = "burst";
+const DAILY_QUOTA_PARAM: &str = "daily-quota";
+
+/// `api-keys` subcommand managing the API keys of the server.
+pub struct ApiKeysTask;
+
+#[async_trait(?Send)]
+impl CliTask for ApiKeysTask {
+    fn subcommand(&self) -> Command {
+        let name = Arg::new(NAME_PARAM)
+            .help("Name of the API key, e.g. the client it's issued to")
+            .required(true);
+
+        Command::new("api-keys")
+            .about("Manage the API keys of the server")
+            .subcommand_required(true)
+            .arg(
+                Arg::new(FILE_PARAM)
+                    .help(format!(
+                        "API key file; defaults to the value of {API_KEYS_FILE_ENV}"
+                    ))
+                    .long(FILE_PARAM)
+                    .global(true)
+                    .value_parser(value_parser!(PathBuf)),
+            )
+            .subcommand(
+                Command::new("create")
+                    .about("Create a new API key and print it")
+                    .arg(name.clone())
+                    .arg(
+                        Arg::new(REQUESTS_PER_MINUTE_PARAM)
+                            .help("Number of requests per minute allowed on average")
+                            .long(REQUESTS_PER_MINUTE_PARAM)
+                            .default_value("600")
+                            .value_parser(value_parser!(NonZeroU32)),
+                    )
+                    .arg(
+                        Arg::new(BURST_PARAM)
+                            .help("Number of requests allowed at once")
+                            .long(BURST_PARAM)
+                            .default_value("100")
+                            .value_parser(value_parser!(NonZeroU32)),
+                    )
+                    .arg(
+                        Arg::new(DAILY_QUOTA_PARAM)
+                            .help("Maximum number of requests per UTC day; unlimited if not set")
+                            .long(DAILY_QUOTA_PARAM)
+                            .value_parser(value_parser!(NonZeroU64)),
+                    ),
+            )
+            .subcommand(Command::new("list").about("List the API keys and their usage"))
+            .subcommand(Command::new("revoke").about("Revoke an API key").arg(name))
+    }
+
+    async fn execute(
+        &mut self,
+        matches: &ArgMatches,
+        _bootstrapper: Bootstrapper<WithConfig>,
+    ) -> cot::Result<()> {
+        let path = matches
+            .get_one::<PathBuf>(FILE_PARAM)
+            .cloned()
+            .or_else(|| std::env::var_os(API_KEYS_FILE_ENV).map(PathBuf::from))
+            .ok_or_else(|| {
+                cot::Error::internal(format!(
+                    "no API key file given; use --{FILE_PARAM} or set {API_KEYS_FILE_ENV}"
+                ))
+            })?;
+
+        match matches.subcommand() {
+            Some(("create", matches)) => {
+                let name = matches.get_one::<String>(NAME_PARAM).expect("required");
+                let limits = ApiKeyLimits {
+                    requests_per_minute: *matches
+                        .get_one(REQUESTS_PER_MINUTE_PARAM)
+                        .expect("default provided"),
+                    burst: *matches.get_one(BURST_PARAM).expect("default provided"),
+                    daily_quota: matches.get_one(DAILY_QUOTA_PARAM).copied(),
+                };
+                let secret = create_api_key(&path, name, limits).map_err(cot::Error::wrap)?;
+                println!("Created API key {name}: {secret}");
+                println!("Store it now; it cannot be shown again.");
+            }
+            Some(("list", _)) => {
+                let keys = read_api_keys(&path).map_err(cot::Error::wrap)?;
+                let usage = read_api_key_usage(&path).map_err(cot::Error::wrap)?;
+                println!(
+                    "{:<24} {:>8} {:>6} {:>12} {:>12} {:>14}  CREATED",
+                    "NAME", "PER MIN", "BURST", "DAILY QUOTA", "TODAY*", "TOTAL*"
+                );
+                for key in keys {
+                    let usage = usage.get(&key.name).copied().unwrap_or_default();
+                    println!(
+                        "{:<24} {:>8} {:>6} {:>12} {:>12} {:>14}  {}",
+                        key.name,
+                        key.requests_per_minute,
+                        key.burst,
+                        key.daily_quota
+                            .map_or_else(|| "-".to_string(), |quota| quota.to_string()),
+                        usage.requests_on_current_day(),
+                        usage.total_requests,
+                        key.created_at
+                    );
+                }
+                println!("* as of the last time the server saved the usage (once a minute)");
+            }
+            Some(("revoke", matches)) => {
+                let name = matches.get_one::<String>(NAME_PARAM).expect("required");
+                revoke_api_key(&path, name).map_err(cot::Error::wrap)?;
+                println!("Revoked API key {name}");
+            }
+            _ => unreachable!("subcommand is required"),
+        }
+
+        Ok(())
+    }
+}
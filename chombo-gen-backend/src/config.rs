@@ -0,0 +1,310 @@
+use std::fmt::{Display, Formatter};
+use std::net::SocketAddr;
+use std::num::{NonZeroU32, NonZeroU64, NonZeroUsize};
+use std::sync::OnceLock;
+use std::time::Duration;
+
+use chombo_gen_common::tile_set::TileSet;
+use chombo_gen_render::RenderLimits;
+use cot::config::ProjectConfig;
+use serde::Deserialize;
+use tracing_subscriber::EnvFilter;
+
+use crate::cors::validate_cors_config;
+
+/// Name of the table in the project config holding [`ChomboGenConfig`].
+const CONFIG_SECTION: &str = "chombo_gen";
+/// Directory searched for `{name}.toml` when there is no file at the given
+/// config path.
+const CONFIG_DIR: &str = "config";
+/// Configs available by name, without a config file next to the binary.
+const BUILT_IN_CONFIGS: [(&str, &str); 2] = [
+    ("dev", include_str!("../config/dev.toml")),
+    ("prod", include_str!("../config/prod.toml")),
+];
+
+/// Address the server listens on by default, like Cot's default command.
+pub const DEFAULT_LISTEN: SocketAddr =
+    SocketAddr::new(std::net::IpAddr::V4(std::net::Ipv4Addr::LOCALHOST), 8000);
+
+static CONFIG: OnceLock<ChomboGenConfig> = OnceLock::new();
+static CONFIG_SOURCE: OnceLock<ConfigSource> = OnceLock::new();
+
+/// Returns the configuration loaded with [`load_config`], or the default one.
+pub fn config() -> &'static ChomboGenConfig {
+    CONFIG.get_or_init(ChomboGenConfig::default)
+}
+
+/// Returns where the configuration loaded with [`load_config`] was read from.
+pub fn config_source() -> Option<&'static ConfigSource> {
+    CONFIG_SOURCE.get()
+}
+
+#[derive(Debug, Clone)]
+pub enum ConfigSource {
+    File(String),
+    BuiltIn(&'static str),
+}
+
+impl Display for ConfigSource {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::File(path) => write!(f, "config file `{path}`"),
+            Self::BuiltIn(name) => write!(f, "built-in config `{name}`"),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Read(String, std::io::Error),
+    Parse(String, cot::Error),
+    Invalid(String, String),
+}
+
+impl Display for ConfigError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Read(config, error) => {
+                write!(f, "could not read config `{config}`: {error}")
+            }
+            Self::Parse(config, error) => {
+                write!(f, "invalid config `{config}`: {error}")
+            }
+            Self::Invalid(config, message) => {
+                write!(f, "invalid config `{config}`: {message}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+/// Settings of the backend, read from the `[chombo_gen]` table of the
+/// project config.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ChomboGenConfig {
+    /// Address the `serve` command listens on; [`DEFAULT_LISTEN`] if not set.
+    pub listen: Option<SocketAddr>,
+    /// Tracing filter used when `RUST_LOG` is not set.
+    pub log_filter: String,
+    /// IDs of the built-in and custom tile sets to make available; all of
+    /// them if not set. Uploaded tile sets are always available.
+    pub enabled_tile_sets: Option<Vec<TileSet>>,
+    pub cors: CorsConfig,
+    pub limits: LimitsConfig,
+    pub cache: CacheConfig,
+}
+
+impl Default for ChomboGenConfig {
+    fn default() -> Self {
+        Self {
+            listen: None,
+            log_filter: "error".to_string(),
+            enabled_tile_sets: None,
+            cors: CorsConfig::default(),
+            limits: LimitsConfig::default(),
+            cache: CacheConfig::default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LimitsConfig {
+    /// Maximum length of a hand description, in bytes.
+    pub max_hand_len: NonZeroUsize,
+    /// Maximum number of pixels of a rendered image.
+    pub max_output_pixels: NonZeroU64,
+    /// Number of hands rendered at once; the number of CPU cores if not set.
+    pub max_concurrent_renders: Option<NonZeroUsize>,
+    /// Number of requests waiting for a render slot before new ones are
+    /// rejected.
+    pub max_queued_renders: NonZeroUsize,
+    /// Maximum number of hands on a print sheet.
+    pub max_print_sheet_hands: NonZeroUsize,
+    /// Maximum number of uploaded tile sets stored on the server.
+    pub max_uploaded_tile_sets: NonZeroUsize,
+    /// Maximum number of uploaded tile sets stored for a single API key.
+    pub max_uploaded_tile_sets_per_key: NonZeroUsize,
+    /// Maximum total size of the uploaded tile sets stored on the server, in
+    /// bytes.
+    pub max_uploaded_bytes: NonZeroU64,
+    /// Maximum total size of the uploaded tile sets stored for a single API
+    /// key, in bytes.
+    pub max_uploaded_bytes_per_key: NonZeroU64,
+    /// Number of API requests a client can make per minute on average; 0
+    /// disables rate limiting of requests without an API key.
+    pub rate_limit_per_minute: u32,
+    /// Number of API requests a client can make at once after being idle.
+    pub rate_limit_burst: NonZeroU32,
+    /// Whether to rate limit requests without an API key per client address,
+    /// taken from the headers set by the reverse proxy. Only to be enabled
+    /// when the backend is not reachable directly, as clients could set the
+    /// headers themselves otherwise.
+    pub trust_proxy_headers: bool,
+}
+
+impl Default for LimitsConfig {
+    fn default() -> Self {
+        Self {
+            max_hand_len: NonZeroUsize::new(100).expect("non-zero"),
+            max_output_pixels: NonZeroU64::new(24_000_000).expect("non-zero"),
+            max_concurrent_renders: None,
+            max_queued_renders: NonZeroUsize::new(64).expect("non-zero"),
+            max_print_sheet_hands: NonZeroUsize::new(100).expect("non-zero"),
+            max_uploaded_tile_sets: NonZeroUsize::new(200).expect("non-zero"),
+            max_uploaded_tile_sets_per_key: NonZeroUsize::new(10).expect("non-zero"),
+            max_uploaded_bytes: NonZeroU64::new(1024 * 1024 * 1024).expect("non-zero"),
+            max_uploaded_bytes_per_key: NonZeroU64::new(128 * 1024 * 1024).expect("non-zero"),
+            rate_limit_per_minute: 120,
+            rate_limit_burst: NonZeroU32::new(60).expect("non-zero"),
+            trust_proxy_headers: false,
+        }
+    }
+}
+
+impl LimitsConfig {
+    #[must_use]
+    pub fn render_limits(&self) -> RenderLimits {
+        RenderLimits {
+            max_hand_len: self.max_hand_len.get(),
+            max_output_pixels: self.max_output_pixels.get(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CacheConfig {
+    /// How long browsers and proxies can cache rendered images.
+    #[serde(with = "humantime_serde")]
+    pub image_max_age: Duration,
+    /// Number of rendered images kept in memory.
+    pub render_cache_capacity: usize,
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        Self {
+            image_max_age: Duration::from_secs(7 * 24 * 60 * 60), // 7 days
+            render_cache_capacity: 512,
+        }
+    }
+}
+
+/// Which websites can call the API from the browser.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CorsConfig {
+    /// Origins of the websites allowed to call the API, e.g.
+    /// `https://example.com`, or `*` for any website.
+    pub allowed_origins: Vec<String>,
+    pub allowed_methods: Vec<String>,
+    /// Request headers allowed in addition to the CORS-safelisted ones.
+    pub allowed_headers: Vec<String>,
+    /// Response headers readable by the websites in addition to the
+    /// CORS-safelisted ones.
+    pub exposed_headers: Vec<String>,
+    /// How long browsers can cache the responses to preflight requests.
+    #[serde(with = "humantime_serde")]
+    pub max_age: Duration,
+}
+
+impl Default for CorsConfig {
+    fn default() -> Self {
+        let to_strings = |values: &[&str]| values.iter().map(ToString::to_string).collect();
+        Self {
+            allowed_origins: Vec::new(),
+            allowed_methods: to_strings(&["GET", "POST", "DELETE"]),
+            allowed_headers: to_strings(&["content-type", "x-api-key", "x-request-id"]),
+            exposed_headers: to_strings(&[
+                "server-timing",
+                "x-request-id",
+                "x-hand-warnings",
+                "ratelimit-limit",
+                "ratelimit-remaining",
+                "ratelimit-reset",
+                "retry-after",
+            ]),
+            max_age: Duration::from_secs(60 * 60), // 1 hour
+        }
+    }
+}
+
+impl ChomboGenConfig {
+    fn validate(&self) -> Result<(), String> {
+        EnvFilter::try_new(&self.log_filter)
+            .map_err(|error| format!("invalid log_filter `{}`: {error}", self.log_filter))?;
+
+        if self
+            .enabled_tile_sets
+            .as_ref()
+            .is_some_and(|tile_sets| tile_sets.is_empty())
+        {
+            return Err("enabled_tile_sets must not be empty".to_string());
+        }
+
+        validate_cors_config(&self.cors)
+    }
+}
+
+/// Reads the project config from the file at `config_name`, then
+/// `config/{config_name}.toml`, or else one of the built-in configs (`dev` or
+/// `prod`), and validates the settings of the backend.
+pub fn load_config(config_name: &str) -> cot::Result<ProjectConfig> {
+    let (source, content) = read_config(config_name).map_err(cot::Error::wrap)?;
+
+    let invalid =
+        |message| cot::Error::wrap(ConfigError::Invalid(config_name.to_string(), message));
+    let mut project_config = ProjectConfig::from_toml(&content)
+        .map_err(|error| cot::Error::wrap(ConfigError::Parse(config_name.to_string(), error)))?;
+    if let Some(key) = project_config
+        .extra
+        .keys()
+        .find(|key| *key != CONFIG_SECTION)
+    {
+        return Err(invalid(format!("unknown setting `{key}`")));
+    }
+    let config: ChomboGenConfig = match project_config.extra.remove(CONFIG_SECTION) {
+        Some(section) => section
+            .try_into()
+            .map_err(|error| invalid(format!("[{CONFIG_SECTION}]: {error}")))?,
+        None => ChomboGenConfig::default(),
+    };
+    config.validate().map_err(invalid)?;
+
+    CONFIG
+        .set(config)
+        .map_err(|_| cot::Error::internal("config loaded more than once"))?;
+    CONFIG_SOURCE.get_or_init(|| source);
+    Ok(project_config)
+}
+
+fn read_config(config_name: &str) -> Result<(ConfigSource, String), ConfigError> {
+    let mut not_found = None;
+    for path in [
+        config_name.to_string(),
+        format!("{CONFIG_DIR}/{config_name}.toml"),
+    ] {
+        match std::fs::read_to_string(&path) {
+            Ok(content) => return Ok((ConfigSource::File(path), content)),
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => {
+                not_found.get_or_insert(error);
+            }
+            Err(error) => return Err(ConfigError::Read(path, error)),
+        }
+    }
+
+    BUILT_IN_CONFIGS
+        .iter()
+        .find(|(name, _)| *name == config_name)
+        .map(|(name, content)| (ConfigSource::BuiltIn(name), (*content).to_string()))
+        .ok_or_else(|| {
+            ConfigError::Read(
+                config_name.to_string(),
+                not_found.expect("config files were looked up"),
+            )
+        })
+}
@@ -0,0 +1,261 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use cot::StatusCode;
+use cot::http::header::{
+    ACCESS_CONTROL_ALLOW_HEADERS, ACCESS_CONTROL_ALLOW_METHODS, ACCESS_CONTROL_ALLOW_ORIGIN,
+    ACCESS_CONTROL_EXPOSE_HEADERS, ACCESS_CONTROL_MAX_AGE, ACCESS_CONTROL_REQUEST_HEADERS,
+    ACCESS_CONTROL_REQUEST_METHOD, ORIGIN, VARY,
+};
+use cot::http::{HeaderMap, HeaderName, HeaderValue, Method, Request, Uri};
+use cot::response::Response;
+use tower::{Layer, Service};
+
+use crate::config::CorsConfig;
+
+/// Only the API can be called from other sites.
+const CORS_PATH_PREFIX: &str = "/api/";
+const ANY_ORIGIN: &str = "*";
+
+/// The parsed [`CorsConfig`], with the header values sent in responses.
+#[derive(Debug)]
+struct CorsPolicy {
+    any_origin: bool,
+    origins: Vec<HeaderValue>,
+    methods: Vec<Method>,
+    allowed_headers: Vec<HeaderName>,
+    allow_methods: Option<HeaderValue>,
+    allow_headers: Option<HeaderValue>,
+    expose_headers: Option<HeaderValue>,
+    max_age: HeaderValue,
+}
+
+impl CorsPolicy {
+    fn new(config: &CorsConfig) -> Result<Self, String> {
+        for origin in &config.allowed_origins {
+            if origin != ANY_ORIGIN && !is_valid_origin(origin) {
+                return Err(format!(
+                    "invalid CORS origin `{origin}`, expected e.g. `https://example.com` or `*`"
+                ));
+            }
+        }
+        let methods = config
+            .allowed_methods
+            .iter()
+            .map(|method| {
+                Method::from_bytes(method.as_bytes())
+                    .map_err(|_| format!("invalid HTTP method `{method}`"))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let allowed_headers = parse_header_names(&config.allowed_headers)?;
+        let exposed_headers = parse_header_names(&config.exposed_headers)?;
+
+        Ok(Self {
+            any_origin: config
+                .allowed_origins
+                .iter()
+                .any(|origin| origin == ANY_ORIGIN),
+            origins: config
+                .allowed_origins
+                .iter()
+                .filter_map(|origin| HeaderValue::try_from(origin.as_str()).ok())
+                .collect(),
+            allow_methods: join_header_value(methods.iter().map(Method::as_str)),
+            allow_headers: join_header_value(allowed_headers.iter().map(HeaderName::as_str)),
+            expose_headers: join_header_value(exposed_headers.iter().map(HeaderName::as_str)),
+            max_age: HeaderValue::from(config.max_age.as_secs()),
+            methods,
+            allowed_headers,
+        })
+    }
+
+    fn is_enabled(&self) -> bool {
+        !self.origins.is_empty()
+    }
+
+    /// Returns the value of `Access-Control-Allow-Origin` for a request from
+    /// `origin`, or `None` if the origin is not allowed.
+    fn allow_origin(&self, origin: Option<&HeaderValue>) -> Option<HeaderValue> {
+        let origin = origin?;
+        if self.any_origin {
+            Some(HeaderValue::from_static(ANY_ORIGIN))
+        } else {
+            self.origins.contains(origin).then(|| origin.clone())
+        }
+    }
+
+    fn add_vary(&self, headers: &mut HeaderMap, values: &'static str) {
+        if !self.any_origin {
+            headers.append(VARY, HeaderValue::from_static(values));
+        }
+    }
+
+    fn is_preflight_allowed(&self, headers: &HeaderMap) -> bool {
+        let method_allowed = headers
+            .get(ACCESS_CONTROL_REQUEST_METHOD)
+            .and_then(|method| Method::from_bytes(method.as_bytes()).ok())
+            .is_some_and(|method| self.methods.contains(&method));
+        let headers_allowed = headers
+            .get_all(ACCESS_CONTROL_REQUEST_HEADERS)
+            .iter()
+            .all(|value| {
+                value.to_str().is_ok_and(|value| {
+                    value
+                        .split(',')
+                        .map(str::trim)
+                        .filter(|name| !name.is_empty())
+                        .all(|name| {
+                            self.allowed_headers
+                                .iter()
+                                .any(|allowed| allowed.as_str().eq_ignore_ascii_case(name))
+                        })
+                })
+            });
+
+        method_allowed && headers_allowed
+    }
+
+    /// Responds to a preflight request; the CORS headers are only included if
+    /// the request is allowed, making the browser block it otherwise.
+    fn preflight_response(&self, headers: &HeaderMap) -> Response {
+        let mut response = Response::default();
+        *response.status_mut() = StatusCode::NO_CONTENT;
+        let response_headers = response.headers_mut();
+        self.add_vary(
+            response_headers,
+            "origin, access-control-request-method, access-control-request-headers",
+        );
+
+        if let Some(allow_origin) = self.allow_origin(headers.get(ORIGIN))
+            && self.is_preflight_allowed(headers)
+        {
+            response_headers.insert(ACCESS_CONTROL_ALLOW_ORIGIN, allow_origin);
+            if let Some(allow_methods) = &self.allow_methods {
+                response_headers.insert(ACCESS_CONTROL_ALLOW_METHODS, allow_methods.clone());
+            }
+            if let Some(allow_headers) = &self.allow_headers {
+                response_headers.insert(ACCESS_CONTROL_ALLOW_HEADERS, allow_headers.clone());
+            }
+            response_headers.insert(ACCESS_CONTROL_MAX_AGE, self.max_age.clone());
+        }
+        response
+    }
+
+    fn add_response_headers(&self, headers: &mut HeaderMap, allow_origin: Option<HeaderValue>) {
+        self.add_vary(headers, "origin");
+        if let Some(allow_origin) = allow_origin {
+            headers.insert(ACCESS_CONTROL_ALLOW_ORIGIN, allow_origin);
+            if let Some(expose_headers) = &self.expose_headers {
+                headers.insert(ACCESS_CONTROL_EXPOSE_HEADERS, expose_headers.clone());
+            }
+        }
+    }
+}
+
+/// Checks that `origin` is in the form sent by browsers in the `Origin`
+/// header: a scheme, a host and an optional port.
+fn is_valid_origin(origin: &str) -> bool {
+    let Ok(uri) = origin.parse::<Uri>() else {
+        return false;
+    };
+    matches!(uri.scheme_str(), Some("http" | "https"))
+        && uri.authority().is_some()
+        && !origin.ends_with('/')
+        && uri.path_and_query().is_none_or(|path| path == "/")
+}
+
+fn parse_header_names(names: &[String]) -> Result<Vec<HeaderName>, String> {
+    names
+        .iter()
+        .map(|name| {
+            HeaderName::from_bytes(name.as_bytes())
+                .map_err(|_| format!("invalid HTTP header name `{name}`"))
+        })
+        .collect()
+}
+
+fn join_header_value<'a>(values: impl Iterator<Item = &'a str>) -> Option<HeaderValue> {
+    let value = values.collect::<Vec<_>>().join(", ");
+    if value.is_empty() {
+        return None;
+    }
+    HeaderValue::try_from(value).ok()
+}
+
+/// Checks that the CORS settings are valid.
+pub fn validate_cors_config(config: &CorsConfig) -> Result<(), String> {
+    CorsPolicy::new(config).map(|_| ())
+}
+
+/// Middleware allowing the websites from the configured origins to call the
+/// API from the browser. It responds to preflight requests itself, and adds
+/// the CORS headers to the responses of the API, including errors.
+#[derive(Debug, Clone)]
+pub struct CorsMiddleware {
+    policy: Arc<CorsPolicy>,
+}
+
+impl CorsMiddleware {
+    pub fn new(config: &CorsConfig) -> Result<Self, String> {
+        Ok(Self {
+            policy: Arc::new(CorsPolicy::new(config)?),
+        })
+    }
+}
+
+impl<S> Layer<S> for CorsMiddleware {
+    type Service = CorsService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        CorsService {
+            policy: Arc::clone(&self.policy),
+            inner,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct CorsService<S> {
+    policy: Arc<CorsPolicy>,
+    inner: S,
+}
+
+impl<ReqBody, S> Service<Request<ReqBody>> for CorsService<S>
+where
+    S: Service<Request<ReqBody>, Response = Response>,
+    S::Future: Send + 'static,
+    S::Error: Send + 'static,
+{
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Response, S::Error>> + Send>>;
+    type Response = Response;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request<ReqBody>) -> Self::Future {
+        if !self.policy.is_enabled() || !request.uri().path().starts_with(CORS_PATH_PREFIX) {
+            return Box::pin(self.inner.call(request));
+        }
+
+        let headers = request.headers();
+        if request.method() == Method::OPTIONS
+            && headers.contains_key(ACCESS_CONTROL_REQUEST_METHOD)
+        {
+            let response = self.policy.preflight_response(headers);
+            return Box::pin(std::future::ready(Ok(response)));
+        }
+
+        let allow_origin = self.policy.allow_origin(headers.get(ORIGIN));
+        let policy = Arc::clone(&self.policy);
+        let future = self.inner.call(request);
+        Box::pin(async move {
+            let mut response = future.await?;
+            policy.add_response_headers(response.headers_mut(), allow_origin);
+            Ok(response)
+        })
+    }
+}
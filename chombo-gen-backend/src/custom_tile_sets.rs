@@ -0,0 +1,276 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use chombo_gen_common::tile_set::TileSet;
+use chombo_gen_render::tile_sets::tile_file_name;
+use image::{ImageError, ImageFormat, ImageReader, RgbaImage, imageops};
+use riichi_hand::raster_renderer::{TileImageResult, TileSet as RasterTileSet};
+use riichi_hand::tiles::ALL_TILES;
+use riichi_hand::{HandTile, Tile, TilePlacement};
+use serde::Deserialize;
+
+use crate::tile_set_registry::{RegisteredTileSet, TileSetSource};
+
+pub const TILE_SETS_DIR_ENV: &str = "CHOMBO_GEN_TILE_SETS_DIR";
+pub const MANIFEST_FILE_NAME: &str = "manifest.toml";
+
+/// Contents of `manifest.toml` describing a custom tile set.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TileSetManifest {
+    pub name: String,
+    pub attribution: String,
+    pub attribution_url: String,
+    pub license: String,
+    pub tile_width: u32,
+    pub tile_height: u32,
+}
+
+#[derive(Debug)]
+pub enum TileSetLoadError {
+    Io(PathBuf, std::io::Error),
+    Manifest(PathBuf, cot::toml::de::Error),
+    Image(PathBuf, ImageError),
+    TileMissing(PathBuf, Tile),
+    InvalidDimensions {
+        path: PathBuf,
+        expected: (u32, u32),
+        actual: (u32, u32),
+    },
+}
+
+impl Display for TileSetLoadError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Io(path, error) => {
+                write!(f, "could not read {}: {error}", path.display())
+            }
+            Self::Manifest(path, error) => {
+                write!(f, "invalid tile set manifest {}: {error}", path.display())
+            }
+            Self::Image(path, error) => {
+                write!(f, "could not load tile image {}: {error}", path.display())
+            }
+            Self::TileMissing(path, tile) => {
+                write!(f, "tile image missing: {} ({tile})", path.display())
+            }
+            Self::InvalidDimensions {
+                path,
+                expected: (expected_width, expected_height),
+                actual: (actual_width, actual_height),
+            } => {
+                write!(
+                    f,
+                    "tile image {} is {actual_width}x{actual_height}, \
+                     expected {expected_width}x{expected_height}",
+                    path.display()
+                )
+            }
+        }
+    }
+}
+
+impl Error for TileSetLoadError {}
+
+/// A tile set made of one complete image per tile face (including the back).
+#[derive(Debug)]
+pub struct CustomTileSet {
+    tiles: HashMap<Tile, RgbaImage>,
+    tile_width: u32,
+    tile_height: u32,
+}
+
+impl RasterTileSet for CustomTileSet {
+    fn tile_image(&self, hand_tile: &HandTile) -> TileImageResult {
+        let image = &self.tiles[&hand_tile.tile];
+
+        if hand_tile.placement == TilePlacement::Normal {
+            Ok(image.clone())
+        } else {
+            Ok(imageops::rotate90(image))
+        }
+    }
+
+    fn tile_width(&self) -> u32 {
+        self.tile_width
+    }
+
+    fn tile_height(&self) -> u32 {
+        self.tile_height
+    }
+}
+
+pub fn tile_image_file_name(tile: &Tile) -> String {
+    format!("{}.png", tile_file_name(tile))
+}
+
+/// Loads every subdirectory of `dir` as a tile set, using the subdirectory
+/// name as the tile set ID. Hidden subdirectories are skipped.
+pub fn load_tile_sets_dir(dir: &Path) -> Result<Vec<RegisteredTileSet>, TileSetLoadError> {
+    tile_set_dirs(dir)?
+        .iter()
+        .map(|tile_set_dir| load_tile_set(tile_set_dir_id(tile_set_dir), tile_set_dir))
+        .collect()
+}
+
+/// Returns the non-hidden subdirectories of `dir`, sorted by name.
+pub fn tile_set_dirs(dir: &Path) -> Result<Vec<PathBuf>, TileSetLoadError> {
+    let mut tile_set_dirs = Vec::new();
+    let entries =
+        std::fs::read_dir(dir).map_err(|error| TileSetLoadError::Io(dir.into(), error))?;
+    for entry in entries {
+        let entry = entry.map_err(|error| TileSetLoadError::Io(dir.into(), error))?;
+        let is_hidden = entry.file_name().to_string_lossy().starts_with('.');
+        if entry.path().is_dir() && !is_hidden {
+            tile_set_dirs.push(entry.path());
+        }
+    }
+    tile_set_dirs.sort();
+    Ok(tile_set_dirs)
+}
+
+/// Returns the ID of the tile set stored in a directory: the directory name.
+#[must_use]
+pub fn tile_set_dir_id(tile_set_dir: &Path) -> TileSet {
+    TileSet::new(
+        tile_set_dir
+            .file_name()
+            .expect("directory entries have file names")
+            .to_string_lossy(),
+    )
+}
+
+/// Loads a tile set from a directory containing `manifest.toml` and a PNG
+/// image for each tile face.
+pub fn load_tile_set(id: TileSet, dir: &Path) -> Result<RegisteredTileSet, TileSetLoadError> {
+    load_tile_set_with(id, dir, read_file_if_exists)
+}
+
+/// Loads a tile set using `read_file` to get the contents of the files in
+/// `location`; `read_file` returns `None` if a file does not exist.
+pub fn load_tile_set_with<F>(
+    id: TileSet,
+    location: &Path,
+    read_file: F,
+) -> Result<RegisteredTileSet, TileSetLoadError>
+where
+    F: FnMut(&Path) -> Result<Option<Vec<u8>>, TileSetLoadError>,
+{
+    let (manifest, tile_set) = read_tile_set_with(location, read_file)?;
+
+    Ok(RegisteredTileSet {
+        id,
+        name: manifest.name,
+        attribution: manifest.attribution,
+        attribution_url: manifest.attribution_url,
+        license: manifest.license,
+        source: TileSetSource::Loaded(Arc::new(tile_set)),
+        vector_tile_set: None,
+        listed: true,
+    })
+}
+
+/// Reads the tile images of a tile set stored in a directory, like
+/// [`load_tile_set`].
+pub fn read_tile_set(dir: &Path) -> Result<CustomTileSet, TileSetLoadError> {
+    read_tile_set_with(dir, read_file_if_exists).map(|(_, tile_set)| tile_set)
+}
+
+/// Reads the manifest of a tile set stored in a directory, without reading
+/// its tile images.
+pub fn read_manifest(dir: &Path) -> Result<TileSetManifest, TileSetLoadError> {
+    read_manifest_with(dir, &mut read_file_if_exists)
+}
+
+fn read_file_if_exists(path: &Path) -> Result<Option<Vec<u8>>, TileSetLoadError> {
+    if !path.is_file() {
+        return Ok(None);
+    }
+    std::fs::read(path)
+        .map(Some)
+        .map_err(|error| TileSetLoadError::Io(path.into(), error))
+}
+
+fn read_tile_set_with<F>(
+    location: &Path,
+    mut read_file: F,
+) -> Result<(TileSetManifest, CustomTileSet), TileSetLoadError>
+where
+    F: FnMut(&Path) -> Result<Option<Vec<u8>>, TileSetLoadError>,
+{
+    let manifest = read_manifest_with(location, &mut read_file)?;
+
+    let mut tiles = HashMap::with_capacity(ALL_TILES.len());
+    for tile in ALL_TILES {
+        let path = location.join(tile_image_file_name(&tile));
+        let Some(data) = read_file(&path)? else {
+            return Err(TileSetLoadError::TileMissing(path, tile));
+        };
+
+        let image = decode_tile_image(&path, &data, &manifest)?;
+        tiles.insert(tile, image);
+    }
+
+    let tile_set = CustomTileSet {
+        tiles,
+        tile_width: manifest.tile_width,
+        tile_height: manifest.tile_height,
+    };
+    Ok((manifest, tile_set))
+}
+
+fn read_manifest_with<F>(
+    location: &Path,
+    read_file: &mut F,
+) -> Result<TileSetManifest, TileSetLoadError>
+where
+    F: FnMut(&Path) -> Result<Option<Vec<u8>>, TileSetLoadError>,
+{
+    let manifest_path = location.join(MANIFEST_FILE_NAME);
+    let manifest = read_file(&manifest_path)?.ok_or_else(|| {
+        TileSetLoadError::Io(
+            manifest_path.clone(),
+            std::io::Error::from(std::io::ErrorKind::NotFound),
+        )
+    })?;
+    parse_manifest(&manifest_path, &manifest)
+}
+
+pub fn parse_manifest(path: &Path, manifest: &[u8]) -> Result<TileSetManifest, TileSetLoadError> {
+    let manifest = std::str::from_utf8(manifest).map_err(|error| {
+        TileSetLoadError::Io(
+            path.into(),
+            std::io::Error::new(std::io::ErrorKind::InvalidData, error),
+        )
+    })?;
+    cot::toml::from_str(manifest).map_err(|error| TileSetLoadError::Manifest(path.into(), error))
+}
+
+/// Decodes a tile image, checking its dimensions before decoding the pixel
+/// data.
+fn decode_tile_image(
+    path: &Path,
+    data: &[u8],
+    manifest: &TileSetManifest,
+) -> Result<RgbaImage, TileSetLoadError> {
+    let reader = || ImageReader::with_format(Cursor::new(data), ImageFormat::Png);
+    let dimensions = reader()
+        .into_dimensions()
+        .map_err(|error| TileSetLoadError::Image(path.into(), error))?;
+    if dimensions != (manifest.tile_width, manifest.tile_height) {
+        return Err(TileSetLoadError::InvalidDimensions {
+            path: path.into(),
+            expected: (manifest.tile_width, manifest.tile_height),
+            actual: dimensions,
+        });
+    }
+
+    Ok(reader()
+        .decode()
+        .map_err(|error| TileSetLoadError::Image(path.into(), error))?
+        .to_rgba8())
+}
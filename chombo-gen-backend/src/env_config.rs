@@ -0,0 +1,18 @@
+use std::fmt::Display;
+use std::str::FromStr;
+
+/// Reads a configuration value from an environment variable, returning `None`
+/// if the variable is not set.
+pub fn env_var<T>(name: &str) -> cot::Result<Option<T>>
+where
+    T: FromStr,
+    T::Err: Display,
+{
+    let Ok(value) = std::env::var(name) else {
+        return Ok(None);
+    };
+    value
+        .parse()
+        .map(Some)
+        .map_err(|error| cot::Error::internal(format!("invalid value of {name}: {error}")))
+}
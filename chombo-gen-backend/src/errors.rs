@@ -4,16 +4,29 @@ use std::fmt::{Display, Formatter};
 use chombo_gen_common::errors::ServiceErrorResponse;
 use cot::StatusCode;
 use cot::error::handler::RequestError;
+use cot::http::HeaderValue;
+use cot::http::header::RETRY_AFTER;
 use cot::json::Json;
-use cot::response::{IntoResponse, WithStatus};
+use cot::response::{IntoResponse, Response};
 use schemars::JsonSchema;
 use tokio::task::JoinError;
 use tracing::error;
 
+use crate::request_id::RequestId;
+
+/// Number of seconds clients are asked to wait before retrying when the
+/// server is overloaded.
+const RETRY_AFTER_SECS: u64 = 5;
+
 #[derive(Debug, Clone, JsonSchema)]
 pub enum ServiceError {
     Internal(String),
     BadRequest(String),
+    Unauthorized(String),
+    Forbidden(String),
+    NotFound(String),
+    PayloadTooLarge(String),
+    ServiceUnavailable(String),
 }
 
 impl Display for ServiceError {
@@ -25,6 +38,21 @@ impl Display for ServiceError {
             Self::BadRequest(msg) => {
                 write!(f, "{msg}")
             }
+            Self::Unauthorized(msg) => {
+                write!(f, "{msg}")
+            }
+            Self::Forbidden(msg) => {
+                write!(f, "{msg}")
+            }
+            Self::NotFound(msg) => {
+                write!(f, "{msg}")
+            }
+            Self::PayloadTooLarge(msg) => {
+                write!(f, "{msg}")
+            }
+            Self::ServiceUnavailable(msg) => {
+                write!(f, "{msg}")
+            }
         }
     }
 }
@@ -32,10 +60,16 @@ impl Display for ServiceError {
 impl Error for ServiceError {}
 
 impl ServiceError {
-    fn get_http_status(&self) -> StatusCode {
+    #[must_use]
+    pub fn get_http_status(&self) -> StatusCode {
         match self {
             Self::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
             Self::BadRequest(_) => StatusCode::BAD_REQUEST,
+            Self::Unauthorized(_) => StatusCode::UNAUTHORIZED,
+            Self::Forbidden(_) => StatusCode::FORBIDDEN,
+            Self::NotFound(_) => StatusCode::NOT_FOUND,
+            Self::PayloadTooLarge(_) => StatusCode::PAYLOAD_TOO_LARGE,
+            Self::ServiceUnavailable(_) => StatusCode::SERVICE_UNAVAILABLE,
         }
     }
 }
@@ -47,11 +81,20 @@ impl From<ServiceError> for cot::Error {
     }
 }
 
-pub async fn error_handler(error: RequestError) -> WithStatus<Json<ServiceErrorResponse>> {
-    Json(ServiceErrorResponse {
+pub async fn error_handler(error: RequestError, request_id: RequestId) -> cot::Result<Response> {
+    let status_code = error.status_code();
+    let mut response = Json(ServiceErrorResponse {
         message: error.to_string(),
+        request_id: Some(request_id.to_string()),
     })
-    .with_status(error.status_code())
+    .with_status(status_code)
+    .into_response()?;
+    if status_code == StatusCode::SERVICE_UNAVAILABLE {
+        response
+            .headers_mut()
+            .insert(RETRY_AFTER, HeaderValue::from(RETRY_AFTER_SECS));
+    }
+    Ok(response)
 }
 
 impl From<JoinError> for ServiceError {
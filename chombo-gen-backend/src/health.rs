@@ -0,0 +1,156 @@
+use std::any::Any;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::OnceLock;
+use std::time::Instant;
+
+use chombo_gen_common::tile_set::TileSet;
+use cot::StatusCode;
+use cot::json::Json;
+use cot::response::{IntoResponse, Response};
+use riichi_hand::parser::HandParser;
+use riichi_hand::raster_renderer::{RasterRenderer, RenderOptions};
+use serde::Serialize;
+use tracing::{error, info};
+
+use crate::tile_set_registry::{TILE_SETS, TileSetSource};
+use crate::tile_sets::PREVIEW_HAND;
+
+/// Tile sets that failed the startup self-test, or the error the whole test
+/// failed with; set once the test is done.
+static SELF_TEST_FAILURES: OnceLock<Result<Vec<TileSetFailure>, String>> = OnceLock::new();
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TileSetFailure {
+    tile_set: TileSet,
+    error: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct HealthResponse {
+    status: &'static str,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct ReadinessResponse {
+    status: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<&'static str>,
+    #[serde(skip_serializing_if = "<[_]>::is_empty")]
+    failed_tile_sets: &'static [TileSetFailure],
+}
+
+/// Renders a test hand with every registered tile set in the background. This
+/// also initializes the built-in tile sets, which are otherwise only loaded
+/// on first use.
+pub fn start_self_test() {
+    tokio::task::spawn_blocking(|| {
+        let start_time = Instant::now();
+        // Loading a built-in tile set panics if its images are broken
+        let result = panic::catch_unwind(run_self_test)
+            .unwrap_or_else(|panic| Err(panic_message(panic.as_ref())));
+
+        match &result {
+            Ok((failures, tested)) if failures.is_empty() => info!(
+                "Self-test render with {tested} tile sets succeeded in {:?}",
+                start_time.elapsed()
+            ),
+            Ok((failures, tested)) => error!(
+                "Self-test failed for {} of {tested} tile sets",
+                failures.len()
+            ),
+            Err(error) => error!("Self-test failed: {error}"),
+        }
+        SELF_TEST_FAILURES
+            .set(result.map(|(failures, _)| failures))
+            .expect("self-test run more than once");
+    });
+}
+
+/// Renders the test hand with the tile sets; returns the tile sets that
+/// failed and the number of tile sets tested.
+fn run_self_test() -> Result<(Vec<TileSetFailure>, usize), String> {
+    let tile_sets = TILE_SETS.all();
+    let hand = HandParser::parse(PREVIEW_HAND).map_err(|error| error.to_string())?;
+
+    let failures = tile_sets
+        .iter()
+        // Tile sets loaded on demand were checked when they were stored,
+        // and loading all of them at once would defeat the purpose
+        .filter(|tile_set| !matches!(tile_set.source, TileSetSource::OnDemand(_)))
+        .filter_map(|tile_set| {
+            let result = panic::catch_unwind(AssertUnwindSafe(|| {
+                let loaded = tile_set
+                    .load_blocking()
+                    .map_err(|error| error.to_string())?;
+                RasterRenderer::render(&hand, &loaded, RenderOptions::default())
+                    .map_err(|error| error.to_string())
+            }))
+            .unwrap_or_else(|_| Err("tile set could not be loaded".to_string()));
+
+            result.err().map(|error| {
+                error!(
+                    "Self-test render with tile set {} failed: {error}",
+                    tile_set.id
+                );
+                TileSetFailure {
+                    tile_set: tile_set.id.clone(),
+                    error,
+                }
+            })
+        })
+        .collect();
+    Ok((failures, tile_sets.len()))
+}
+
+fn panic_message(panic: &(dyn Any + Send)) -> String {
+    panic
+        .downcast_ref::<&str>()
+        .map(ToString::to_string)
+        .or_else(|| panic.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "self-test panicked".to_string())
+}
+
+/// Liveness probe; succeeds as long as the server is able to respond.
+pub async fn health() -> cot::Result<Response> {
+    Json(HealthResponse { status: "ok" }).into_response()
+}
+
+/// Readiness probe; succeeds once the startup self-test has passed.
+pub async fn ready() -> cot::Result<Response> {
+    let (status_code, response) = match SELF_TEST_FAILURES.get() {
+        None => (
+            StatusCode::SERVICE_UNAVAILABLE,
+            ReadinessResponse {
+                status: "starting",
+                error: None,
+                failed_tile_sets: &[],
+            },
+        ),
+        Some(Ok(failures)) if failures.is_empty() => (
+            StatusCode::OK,
+            ReadinessResponse {
+                status: "ok",
+                error: None,
+                failed_tile_sets: &[],
+            },
+        ),
+        Some(Ok(failures)) => (
+            StatusCode::SERVICE_UNAVAILABLE,
+            ReadinessResponse {
+                status: "failed",
+                error: None,
+                failed_tile_sets: failures,
+            },
+        ),
+        Some(Err(error)) => (
+            StatusCode::SERVICE_UNAVAILABLE,
+            ReadinessResponse {
+                status: "failed",
+                error: Some(error),
+                failed_tile_sets: &[],
+            },
+        ),
+    };
+
+    Json(response).with_status(status_code).into_response()
+}
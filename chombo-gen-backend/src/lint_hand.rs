@@ -0,0 +1,27 @@
+use chombo_gen_common::hand::HandLintResponse;
+use chombo_gen_render::lint::lint;
+use cot::json::Json;
+use cot::request::extractors::UrlQuery;
+use riichi_hand::parser::HandParser;
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+use crate::errors::ServiceError;
+use crate::render_hand::validate_hand_len;
+
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+pub struct LintHandParams {
+    hand: String,
+}
+
+pub async fn lint_hand(
+    UrlQuery(LintHandParams { hand }): UrlQuery<LintHandParams>,
+) -> Result<Json<HandLintResponse>, ServiceError> {
+    validate_hand_len(&hand)?;
+
+    let hand_obj = HandParser::parse(&hand)?;
+
+    Ok(Json(HandLintResponse {
+        warnings: lint(&hand_obj),
+    }))
+}
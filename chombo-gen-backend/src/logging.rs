@@ -0,0 +1,124 @@
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{EnvFilter, Layer, Registry};
+
+use crate::config::config;
+use crate::env_config::env_var;
+
+pub const LOG_FORMAT_ENV: &str = "CHOMBO_GEN_LOG_FORMAT";
+pub const OTLP_ENDPOINT_ENV: &str = "CHOMBO_GEN_OTLP_ENDPOINT";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LogFormat {
+    /// Human-readable logs.
+    #[default]
+    Text,
+    /// One JSON object per line, including the fields of the current spans.
+    Json,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseLogFormatError(String);
+
+impl Display for ParseLogFormatError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "unknown log format `{}`, expected `text` or `json`",
+            self.0
+        )
+    }
+}
+
+impl FromStr for LogFormat {
+    type Err = ParseLogFormatError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(Self::Text),
+            "json" => Ok(Self::Json),
+            _ => Err(ParseLogFormatError(s.to_string())),
+        }
+    }
+}
+
+type BoxedLayer = Box<dyn Layer<Registry> + Send + Sync>;
+
+/// Sets up the global tracing subscriber: logs filtered with `RUST_LOG` (or
+/// the configured filter) and formatted as configured with
+/// [`LOG_FORMAT_ENV`], and optionally trace export to an OpenTelemetry
+/// collector.
+pub fn init_logging() -> cot::Result<()> {
+    let filter = match std::env::var(EnvFilter::DEFAULT_ENV) {
+        Ok(filter) => EnvFilter::try_new(&filter).map_err(|error| {
+            cot::Error::internal(format!(
+                "invalid value of {}: {error}",
+                EnvFilter::DEFAULT_ENV
+            ))
+        })?,
+        Err(_) => EnvFilter::try_new(&config().log_filter).map_err(cot::Error::wrap)?,
+    };
+    let format = env_var::<LogFormat>(LOG_FORMAT_ENV)?.unwrap_or_default();
+    let fmt_layer = match format {
+        LogFormat::Text => tracing_subscriber::fmt::layer().boxed(),
+        LogFormat::Json => tracing_subscriber::fmt::layer().json().boxed(),
+    };
+
+    tracing_subscriber::registry()
+        .with(otlp_layer()?)
+        .with(fmt_layer.with_filter(filter))
+        .init();
+
+    Ok(())
+}
+
+/// Returns a layer exporting the spans of the backend over OTLP/HTTP to the
+/// endpoint configured with [`OTLP_ENDPOINT_ENV`], if any.
+#[cfg(feature = "otlp")]
+fn otlp_layer() -> cot::Result<Option<BoxedLayer>> {
+    use opentelemetry::trace::TracerProvider;
+    use opentelemetry_otlp::{SpanExporter, WithExportConfig};
+    use opentelemetry_sdk::Resource;
+    use opentelemetry_sdk::trace::SdkTracerProvider;
+    use tracing::Level;
+    use tracing_subscriber::filter::Targets;
+
+    let Some(endpoint) = env_var::<String>(OTLP_ENDPOINT_ENV)? else {
+        return Ok(None);
+    };
+    let exporter = SpanExporter::builder()
+        .with_http()
+        .with_endpoint(endpoint)
+        .build()
+        .map_err(cot::Error::wrap)?;
+    let provider = SdkTracerProvider::builder()
+        .with_batch_exporter(exporter)
+        .with_resource(
+            Resource::builder()
+                .with_service_name(env!("CARGO_PKG_NAME"))
+                .build(),
+        )
+        .build();
+    let tracer = provider.tracer(env!("CARGO_PKG_NAME"));
+    opentelemetry::global::set_tracer_provider(provider);
+
+    // Only export the spans of the backend itself, regardless of `RUST_LOG`
+    let layer = tracing_opentelemetry::layer()
+        .with_tracer(tracer)
+        .with_filter(Targets::new().with_target(env!("CARGO_CRATE_NAME"), Level::INFO));
+    Ok(Some(layer.boxed()))
+}
+
+#[cfg(not(feature = "otlp"))]
+fn otlp_layer() -> cot::Result<Option<BoxedLayer>> {
+    if std::env::var_os(OTLP_ENDPOINT_ENV).is_some() {
+        return Err(cot::Error::internal(format!(
+            "{OTLP_ENDPOINT_ENV} is set, but the backend was built without the `otlp` feature"
+        )));
+    }
+
+    Ok(None)
+}
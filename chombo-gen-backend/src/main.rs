@@ -1,22 +1,96 @@
-use std::time::Duration;
+use std::path::Path;
 
 use async_trait::async_trait;
-use cot::cli::CliMetadata;
-use cot::config::{ProjectConfig, StaticFilesConfig, StaticFilesPathRewriteMode};
+use cot::cli::{Cli, CliMetadata};
+use cot::config::ProjectConfig;
 use cot::error::handler::DynErrorPageHandler;
 use cot::openapi::swagger_ui::SwaggerUi;
 use cot::project::{MiddlewareContext, RegisterAppsContext, RootHandler, RootHandlerBuilder};
-use cot::router::method::openapi::api_get;
+use cot::router::method::openapi::{api_delete, api_get, api_post};
 use cot::router::{Route, Router};
 use cot::static_files::StaticFilesMiddleware;
 use cot::{App, AppBuilder, Project, ProjectContext};
-use tracing_subscriber::util::SubscriberInitExt;
+use tracing::{info, warn};
 
+use crate::api_keys::{api_key_store, api_key_usage, init_api_key_store};
+use crate::api_keys_cli::ApiKeysTask;
+use crate::config::{config, config_source, load_config};
+use crate::cors::CorsMiddleware;
+use crate::custom_tile_sets::{TILE_SETS_DIR_ENV, load_tile_sets_dir};
 use crate::errors::error_handler;
-use crate::render_hand::new_render_hand;
+use crate::health::{health, ready, start_self_test};
+use crate::lint_hand::lint_hand;
+use crate::logging::init_logging;
+use crate::metrics::metrics;
+use crate::normalize_hand::normalize_hand;
+use crate::parse_hand::parse_hand;
+use crate::rate_limit::{RateLimitMiddleware, anonymous_rate_limit, init_anonymous_rate_limit};
+use crate::render_cli::RenderTask;
+use crate::render_hand::{new_render_hand, render_parsed_hand};
+use crate::render_limiter::init_render_limiter;
+use crate::render_pdf::{render_hand_pdf, render_print_sheet};
+use crate::request_id::RequestIdMiddleware;
+use crate::serve_cli::{ServeTask, serve_command_running};
+use crate::tile_set_registry::TILE_SETS;
+use crate::tile_sets::{list_tile_sets, tile_set_preview};
+use crate::uploaded_tile_sets::{
+    UPLOADED_TILE_SETS_DIR_ENV, delete_tile_set, init_uploaded_tile_sets, upload_tile_set,
+};
 
+mod api_keys;
+mod api_keys_cli;
+mod config;
+mod cors;
+mod custom_tile_sets;
+mod env_config;
 mod errors;
+mod health;
+mod lint_hand;
+mod logging;
+mod metrics;
+mod normalize_hand;
+mod parse_hand;
+mod rate_limit;
+mod render_cache;
+mod render_cli;
 mod render_hand;
+mod render_limiter;
+mod render_pdf;
+mod request_id;
+mod serve_cli;
+mod server_timing;
+mod tile_set_registry;
+mod tile_sets;
+mod uploaded_tile_sets;
+
+/// Registers the custom and uploaded tile sets, and disables the tile sets
+/// not enabled in the config.
+fn load_tile_sets() -> cot::Result<()> {
+    if let Some(tile_sets_dir) = std::env::var_os(TILE_SETS_DIR_ENV) {
+        for tile_set in load_tile_sets_dir(Path::new(&tile_sets_dir)).map_err(cot::Error::wrap)? {
+            info!("Loaded custom tile set {} ({})", tile_set.id, tile_set.name);
+            TILE_SETS.register(tile_set)?;
+        }
+    }
+
+    if let Some(enabled_tile_sets) = &config().enabled_tile_sets {
+        TILE_SETS.retain(enabled_tile_sets).map_err(|id| {
+            cot::Error::internal(format!("unknown tile set `{id}` in enabled_tile_sets"))
+        })?;
+    }
+
+    if let Some(uploads_dir) = std::env::var_os(UPLOADED_TILE_SETS_DIR_ENV) {
+        for tile_set in init_uploaded_tile_sets(uploads_dir.into()).map_err(cot::Error::wrap)? {
+            info!(
+                "Registered uploaded tile set {} ({})",
+                tile_set.id, tile_set.name
+            );
+            TILE_SETS.register(tile_set)?;
+        }
+    }
+
+    Ok(())
+}
 
 struct ChomboGenApp;
 
@@ -27,15 +101,67 @@ impl App for ChomboGenApp {
     }
 
     async fn init(&self, _context: &mut ProjectContext) -> cot::Result<()> {
-        tracing_subscriber::fmt()
-            .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
-            .finish()
-            .init();
+        init_logging()?;
+        if let Some(source) = config_source() {
+            info!("Using the {source}");
+        }
+        if let Some(listen) = config().listen
+            && !serve_command_running()
+        {
+            warn!(
+                "`listen` is set to {listen} in the config, but it's only used by the `serve` \
+                 command; listening on the address given with `-l` instead"
+            );
+        }
+
+        let render_limiter = init_render_limiter(&config().limits)?;
+        info!(
+            "Rendering at most {} hands at once, with at most {} waiting",
+            render_limiter.max_concurrent(),
+            render_limiter.max_queued()
+        );
+
+        load_tile_sets()?;
+
+        if let Some(api_keys) = api_key_store() {
+            api_keys.start_reloading();
+        }
+        start_self_test();
+
         Ok(())
     }
 
     fn router(&self) -> Router {
-        Router::with_urls([Route::with_api_handler("/hand/", api_get(new_render_hand))])
+        Router::with_urls([
+            Route::with_api_handler("/hand/", api_get(new_render_hand).post(render_parsed_hand)),
+            Route::with_api_handler("/hand/normalize", api_get(normalize_hand)),
+            Route::with_api_handler("/hand/lint", api_get(lint_hand)),
+            Route::with_api_handler("/hand/parse", api_get(parse_hand)),
+            Route::with_api_handler("/hand/pdf", api_get(render_hand_pdf)),
+            Route::with_api_handler("/print-sheet", api_post(render_print_sheet)),
+            Route::with_api_handler("/tile-sets", api_get(list_tile_sets).post(upload_tile_set)),
+            Route::with_api_handler("/tile-sets/{tile_set}", api_delete(delete_tile_set)),
+            Route::with_api_handler("/tile-sets/{tile_set}/preview", api_get(tile_set_preview)),
+            Route::with_api_handler("/api-key", api_get(api_key_usage)),
+        ])
+    }
+}
+
+/// Endpoints for monitoring, served outside `/api` so that they are not
+/// exposed by the reverse proxy.
+struct MonitoringApp;
+
+impl App for MonitoringApp {
+    fn name(&self) -> &'static str {
+        "monitoring"
+    }
+
+    fn router(&self) -> Router {
+        Router::with_urls([
+            Route::with_handler("/health", health),
+            Route::with_handler("/ready", ready),
+            Route::with_handler("/metrics", metrics),
+        ])
     }
 }
 
@@ -45,25 +171,35 @@ impl Project for ChomboGenProject {
         cot::cli::metadata!()
     }
 
-    fn config(&self, _config_name: &str) -> cot::Result<ProjectConfig> {
-        Ok(ProjectConfig::builder()
-            .static_files(
-                StaticFilesConfig::builder()
-                    .rewrite(StaticFilesPathRewriteMode::QueryParam)
-                    .cache_timeout(Duration::from_secs(365 * 24 * 60 * 60)) // 1 year
-                    .build(),
-            )
-            .build())
+    fn register_tasks(&self, cli: &mut Cli) {
+        cli.add_task(ApiKeysTask);
+        cli.add_task(RenderTask);
+        cli.add_task(ServeTask);
+    }
+
+    fn config(&self, config_name: &str) -> cot::Result<ProjectConfig> {
+        let project_config = load_config(config_name)?;
+        // Read here rather than in `middlewares`, which can't return errors
+        init_anonymous_rate_limit(&config().limits)?;
+        init_api_key_store().map_err(cot::Error::wrap)?;
+        Ok(project_config)
     }
 
     fn register_apps(&self, apps: &mut AppBuilder, _context: &RegisterAppsContext) {
         apps.register_with_views(SwaggerUi::new(), "/swagger");
         apps.register_with_views(ChomboGenApp, "/api");
+        apps.register_with_views(MonitoringApp, "");
     }
 
     fn middlewares(&self, handler: RootHandlerBuilder, context: &MiddlewareContext) -> RootHandler {
         handler
             .middleware(StaticFilesMiddleware::from_context(context)) // needed for Swagger UI
+            .middleware(RateLimitMiddleware::new(
+                anonymous_rate_limit(),
+                api_key_store(),
+            ))
+            .middleware(CorsMiddleware::new(&config().cors).expect("invalid CORS configuration"))
+            .middleware(RequestIdMiddleware)
             .build()
     }
 
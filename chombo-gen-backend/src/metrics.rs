@@ -0,0 +1,176 @@
+use std::sync::LazyLock;
+use std::time::Duration;
+
+use chombo_gen_common::tile_set::TileSet;
+use cot::http::header::CONTENT_TYPE;
+use cot::response::{IntoResponse, Response};
+use cot::{Body, StatusCode};
+use prometheus::{
+    Encoder, Histogram, HistogramOpts, HistogramVec, IntCounterVec, IntGauge, Opts, Registry,
+    TextEncoder, exponential_buckets,
+};
+
+use crate::render_limiter::render_limiter;
+use crate::tile_set_registry::TILE_SETS;
+
+const NAMESPACE: &str = "chombo_gen";
+/// Label used for uploaded tile sets, which would otherwise create a new time
+/// series for every upload.
+const UPLOADED_TILE_SET_LABEL: &str = "uploaded";
+const UNKNOWN_TILE_SET_LABEL: &str = "unknown";
+
+pub static METRICS: LazyLock<Metrics> = LazyLock::new(Metrics::new);
+
+#[derive(Debug)]
+pub struct Metrics {
+    registry: Registry,
+    render_requests: IntCounterVec,
+    render_duration: HistogramVec,
+    encode_duration: HistogramVec,
+    output_size: HistogramVec,
+    queue_wait: Histogram,
+    queue_depth: IntGauge,
+    renders_in_progress: IntGauge,
+}
+
+impl Metrics {
+    fn new() -> Self {
+        let render_requests = IntCounterVec::new(
+            Opts::new(
+                "render_requests_total",
+                "Number of hand render requests, by tile set and response status",
+            )
+            .namespace(NAMESPACE),
+            &["tile_set", "status"],
+        )
+        .expect("valid metric");
+        let render_duration = HistogramVec::new(
+            HistogramOpts::new("render_duration_seconds", "Time spent rendering hands")
+                .namespace(NAMESPACE)
+                .buckets(exponential_buckets(0.001, 2.0, 13).expect("valid buckets")),
+            &["tile_set"],
+        )
+        .expect("valid metric");
+        let encode_duration = HistogramVec::new(
+            HistogramOpts::new(
+                "encode_duration_seconds",
+                "Time spent encoding rendered hands as PNG",
+            )
+            .namespace(NAMESPACE)
+            .buckets(exponential_buckets(0.001, 2.0, 13).expect("valid buckets")),
+            &["tile_set"],
+        )
+        .expect("valid metric");
+        let output_size = HistogramVec::new(
+            HistogramOpts::new("output_size_bytes", "Size of the rendered PNG images")
+                .namespace(NAMESPACE)
+                .buckets(exponential_buckets(1024.0, 4.0, 10).expect("valid buckets")),
+            &["tile_set"],
+        )
+        .expect("valid metric");
+        let queue_wait = Histogram::with_opts(
+            HistogramOpts::new(
+                "render_queue_wait_seconds",
+                "Time spent waiting for a render slot",
+            )
+            .namespace(NAMESPACE)
+            .buckets(exponential_buckets(0.001, 2.0, 15).expect("valid buckets")),
+        )
+        .expect("valid metric");
+        let queue_depth = IntGauge::with_opts(
+            Opts::new(
+                "render_queue_depth",
+                "Number of requests waiting for a render slot",
+            )
+            .namespace(NAMESPACE),
+        )
+        .expect("valid metric");
+        let renders_in_progress = IntGauge::with_opts(
+            Opts::new(
+                "renders_in_progress",
+                "Number of hands being rendered on the blocking thread pool",
+            )
+            .namespace(NAMESPACE),
+        )
+        .expect("valid metric");
+
+        let registry = Registry::new();
+        for collector in [
+            Box::new(render_requests.clone()) as Box<dyn prometheus::core::Collector>,
+            Box::new(render_duration.clone()),
+            Box::new(encode_duration.clone()),
+            Box::new(output_size.clone()),
+            Box::new(queue_wait.clone()),
+            Box::new(queue_depth.clone()),
+            Box::new(renders_in_progress.clone()),
+        ] {
+            registry
+                .register(collector)
+                .expect("metric names are unique");
+        }
+
+        Self {
+            registry,
+            render_requests,
+            render_duration,
+            encode_duration,
+            output_size,
+            queue_wait,
+            queue_depth,
+            renders_in_progress,
+        }
+    }
+
+    pub fn record_render_request(&self, tile_set: &TileSet, status: StatusCode) {
+        self.render_requests
+            .with_label_values(&[tile_set_label(tile_set).as_str(), status.as_str()])
+            .inc();
+    }
+
+    pub fn record_queue_wait(&self, wait_time: Duration) {
+        self.queue_wait.observe(wait_time.as_secs_f64());
+    }
+
+    pub fn record_render(
+        &self,
+        tile_set: &TileSet,
+        render_duration: Duration,
+        encode_duration: Duration,
+        output_size: usize,
+    ) {
+        let label = tile_set_label(tile_set);
+        self.render_duration
+            .with_label_values(&[label.as_str()])
+            .observe(render_duration.as_secs_f64());
+        self.encode_duration
+            .with_label_values(&[label.as_str()])
+            .observe(encode_duration.as_secs_f64());
+        self.output_size
+            .with_label_values(&[label.as_str()])
+            .observe(output_size as f64);
+    }
+
+    fn encode(&self) -> Result<String, prometheus::Error> {
+        let limiter = render_limiter();
+        self.queue_depth.set(limiter.queued() as i64);
+        self.renders_in_progress.set(limiter.in_progress() as i64);
+
+        TextEncoder::new().encode_to_string(&self.registry.gather())
+    }
+}
+
+fn tile_set_label(tile_set: &TileSet) -> String {
+    match TILE_SETS.get(tile_set) {
+        Ok(registered) if registered.listed => tile_set.id().to_string(),
+        Ok(_) => UPLOADED_TILE_SET_LABEL.to_string(),
+        Err(_) => UNKNOWN_TILE_SET_LABEL.to_string(),
+    }
+}
+
+/// Returns the metrics in the Prometheus text format.
+pub async fn metrics() -> cot::Result<Response> {
+    let metrics = METRICS.encode().map_err(cot::Error::wrap)?;
+    Response::new(Body::fixed(metrics))
+        .with_header(CONTENT_TYPE, TextEncoder::new().format_type())
+        .into_response()
+}
@@ -0,0 +1,29 @@
+use chombo_gen_common::hand::NormalizedHandResponse;
+use chombo_gen_render::normalize::normalize;
+use cot::json::Json;
+use cot::request::extractors::UrlQuery;
+use riichi_hand::parser::HandParser;
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+use crate::errors::ServiceError;
+use crate::render_hand::validate_hand_len;
+
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+pub struct NormalizeHandParams {
+    hand: String,
+    #[serde(default)]
+    sort: bool,
+}
+
+pub async fn normalize_hand(
+    UrlQuery(NormalizeHandParams { hand, sort }): UrlQuery<NormalizeHandParams>,
+) -> Result<Json<NormalizedHandResponse>, ServiceError> {
+    validate_hand_len(&hand)?;
+
+    let hand_obj = HandParser::parse(&hand)?;
+
+    Ok(Json(NormalizedHandResponse {
+        hand: normalize(&hand_obj, sort),
+    }))
+}
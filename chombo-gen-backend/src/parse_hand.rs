@@ -0,0 +1,126 @@
+use chombo_gen_common::hand::{ParsedGroup, ParsedHand, ParsedTile, Suit};
+use cot::json::Json;
+use cot::request::extractors::UrlQuery;
+use riichi_hand::parser::HandParser;
+use riichi_hand::tiles::ANY;
+use riichi_hand::{Hand, HandGroup, HandTile, Suite, Tile, TilePlacement, TileValue};
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+use crate::errors::ServiceError;
+use crate::render_hand::validate_hand_len;
+
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+pub struct ParseHandParams {
+    hand: String,
+}
+
+pub async fn parse_hand(
+    UrlQuery(ParseHandParams { hand }): UrlQuery<ParseHandParams>,
+) -> Result<Json<ParsedHand>, ServiceError> {
+    validate_hand_len(&hand)?;
+
+    let hand_obj = HandParser::parse(&hand)?;
+
+    Ok(Json(to_parsed_hand(&hand_obj)))
+}
+
+pub fn to_parsed_hand(hand: &Hand) -> ParsedHand {
+    ParsedHand {
+        groups: hand
+            .groups()
+            .iter()
+            .map(|group| ParsedGroup {
+                tiles: group.iter().map(to_parsed_tile).collect(),
+            })
+            .collect(),
+    }
+}
+
+fn to_parsed_tile(hand_tile: &HandTile) -> ParsedTile {
+    let suit = match hand_tile.tile.suite {
+        Suite::Manzu => Some(Suit::Manzu),
+        Suite::Pinzu => Some(Suit::Pinzu),
+        Suite::Souzu => Some(Suit::Souzu),
+        Suite::Honor => Some(Suit::Honor),
+        Suite::Any => None,
+    };
+    let red = suit.is_some_and(|suit| suit != Suit::Honor) && hand_tile.tile.value.0 == 0;
+    let value = suit.map(|_| if red { 5 } else { hand_tile.tile.value.0 });
+
+    ParsedTile {
+        suit,
+        value,
+        red,
+        rotated: hand_tile.placement != TilePlacement::Normal,
+        shifted: hand_tile.placement == TilePlacement::RotatedAndShifted,
+        face_down: suit.is_none(),
+    }
+}
+
+/// Converts a structured hand back into a [`Hand`], validating every tile.
+///
+/// Errors point at the offending tile using a JSON path starting at `path`
+/// (e.g. `hand.groups[1].tiles[0]`).
+pub fn from_parsed_hand(hand: &ParsedHand, path: &str) -> Result<Hand, ServiceError> {
+    let groups = hand
+        .groups
+        .iter()
+        .enumerate()
+        .map(|(group_index, group)| {
+            group
+                .tiles
+                .iter()
+                .enumerate()
+                .map(|(tile_index, tile)| {
+                    from_parsed_tile(tile).map_err(|message| {
+                        ServiceError::BadRequest(format!(
+                            "{path}.groups[{group_index}].tiles[{tile_index}]: {message}"
+                        ))
+                    })
+                })
+                .collect::<Result<HandGroup, _>>()
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(Hand::new(groups))
+}
+
+fn from_parsed_tile(parsed_tile: &ParsedTile) -> Result<HandTile, String> {
+    let placement = match (parsed_tile.rotated, parsed_tile.shifted) {
+        (false, false) => TilePlacement::Normal,
+        (true, false) => TilePlacement::Rotated,
+        (true, true) => TilePlacement::RotatedAndShifted,
+        (false, true) => return Err("shifted tiles must also be rotated".to_string()),
+    };
+
+    if parsed_tile.face_down {
+        if parsed_tile.suit.is_some() || parsed_tile.value.is_some() || parsed_tile.red {
+            return Err("face-down tiles can't have a suit, value or red flag".to_string());
+        }
+        return Ok(HandTile::new(ANY, placement));
+    }
+
+    let suit = parsed_tile.suit.ok_or("missing suit")?;
+    let value = parsed_tile.value.ok_or("missing value")?;
+    let suite = match suit {
+        Suit::Manzu => Suite::Manzu,
+        Suit::Pinzu => Suite::Pinzu,
+        Suit::Souzu => Suite::Souzu,
+        Suit::Honor => Suite::Honor,
+    };
+
+    let value = if parsed_tile.red {
+        if suit == Suit::Honor || value != 5 {
+            return Err("only fives of manzu, pinzu and souzu can be red".to_string());
+        }
+        0
+    } else if value == 0 {
+        return Err(format!("invalid value {value}; use `red` for red fives"));
+    } else {
+        value
+    };
+
+    let tile = Tile::new(suite, TileValue(value)).map_err(|error| error.to_string())?;
+    Ok(HandTile::new(tile, placement))
+}
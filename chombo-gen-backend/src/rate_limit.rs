@@ -0,0 +1,434 @@
+use std::collections::HashMap;
+use std::future::Future;
+use std::num::{NonZeroU32, NonZeroU64};
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::task::{Context, Poll};
+use std::time::Instant;
+
+use chombo_gen_common::errors::ServiceErrorResponse;
+use cot::StatusCode;
+use cot::http::header::RETRY_AFTER;
+use cot::http::{HeaderMap, HeaderName, HeaderValue, Request};
+use cot::json::Json;
+use cot::response::{IntoResponse, Response};
+use tower::{Layer, Service};
+use tracing::error;
+
+use crate::api_keys::{API_KEY_HEADER, ApiKey, ApiKeyStore, AuthenticatedApiKey, QuotaState};
+use crate::config::LimitsConfig;
+use crate::env_config::env_var;
+use crate::request_id::RequestId;
+
+pub const RATE_LIMIT_PER_MINUTE_ENV: &str = "CHOMBO_GEN_RATE_LIMIT_PER_MINUTE";
+pub const RATE_LIMIT_BURST_ENV: &str = "CHOMBO_GEN_RATE_LIMIT_BURST";
+pub const TRUST_PROXY_HEADERS_ENV: &str = "CHOMBO_GEN_TRUST_PROXY_HEADERS";
+/// Only the API is rate limited; static files and Swagger UI are not.
+const RATE_LIMITED_PATH_PREFIX: &str = "/api/";
+/// Number of requests after which the buckets of clients that haven't made
+/// any requests recently are removed.
+const PRUNE_INTERVAL: u64 = 1024;
+/// Bucket shared by all anonymous requests when the client address is not
+/// known.
+const SHARED_ANONYMOUS_BUCKET: &str = "anonymous";
+
+const X_REAL_IP: HeaderName = HeaderName::from_static("x-real-ip");
+const X_FORWARDED_FOR: HeaderName = HeaderName::from_static("x-forwarded-for");
+const RATE_LIMIT_LIMIT: HeaderName = HeaderName::from_static("ratelimit-limit");
+const RATE_LIMIT_REMAINING: HeaderName = HeaderName::from_static("ratelimit-remaining");
+const RATE_LIMIT_RESET: HeaderName = HeaderName::from_static("ratelimit-reset");
+
+static ANONYMOUS_RATE_LIMIT: OnceLock<Option<AnonymousRateLimit>> = OnceLock::new();
+
+/// Reads the limit of anonymous requests with
+/// [`AnonymousRateLimit::from_config`]. Called when the project config is
+/// loaded, so that invalid limits are reported at startup.
+pub fn init_anonymous_rate_limit(limits: &LimitsConfig) -> cot::Result<()> {
+    let rate_limit = AnonymousRateLimit::from_config(limits)?;
+    ANONYMOUS_RATE_LIMIT.get_or_init(|| rate_limit);
+    Ok(())
+}
+
+/// Returns the limit read with [`init_anonymous_rate_limit`].
+#[must_use]
+pub fn anonymous_rate_limit() -> Option<AnonymousRateLimit> {
+    *ANONYMOUS_RATE_LIMIT
+        .get()
+        .expect("rate limit is read with the project config")
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct RateLimitConfig {
+    /// Number of requests a client can make per minute on average.
+    pub requests_per_minute: NonZeroU32,
+    /// Number of requests a client can make at once after being idle.
+    pub burst: NonZeroU32,
+}
+
+impl RateLimitConfig {
+    /// Reads the limits from the config, overridden by the environment
+    /// variables; returns `None` if anonymous requests are not rate limited
+    /// (the limit per minute is set to 0).
+    pub fn from_config(limits: &LimitsConfig) -> cot::Result<Option<Self>> {
+        let requests_per_minute =
+            env_var::<u32>(RATE_LIMIT_PER_MINUTE_ENV)?.unwrap_or(limits.rate_limit_per_minute);
+        let Some(requests_per_minute) = NonZeroU32::new(requests_per_minute) else {
+            return Ok(None);
+        };
+        let burst = env_var::<NonZeroU32>(RATE_LIMIT_BURST_ENV)?.unwrap_or(limits.rate_limit_burst);
+
+        Ok(Some(Self {
+            requests_per_minute,
+            burst,
+        }))
+    }
+
+    fn tokens_per_second(&self) -> f64 {
+        f64::from(self.requests_per_minute.get()) / 60.0
+    }
+}
+
+/// Limit of the requests without an API key.
+#[derive(Debug, Clone, Copy)]
+pub struct AnonymousRateLimit {
+    pub config: RateLimitConfig,
+    /// Whether the clients are told apart by the address in the `X-Real-IP`
+    /// and `X-Forwarded-For` headers. Otherwise, all anonymous requests share
+    /// a single bucket, as the headers could be set by the clients themselves.
+    pub trust_proxy_headers: bool,
+}
+
+impl AnonymousRateLimit {
+    /// Reads the limit with [`RateLimitConfig::from_config`], and whether to
+    /// trust the proxy headers from the config, overridden by the environment
+    /// variable.
+    pub fn from_config(limits: &LimitsConfig) -> cot::Result<Option<Self>> {
+        let Some(config) = RateLimitConfig::from_config(limits)? else {
+            return Ok(None);
+        };
+        let trust_proxy_headers =
+            env_var::<bool>(TRUST_PROXY_HEADERS_ENV)?.unwrap_or(limits.trust_proxy_headers);
+
+        Ok(Some(Self {
+            config,
+            trust_proxy_headers,
+        }))
+    }
+}
+
+#[derive(Debug)]
+struct TokenBucket {
+    tokens: f64,
+    last_refill: Instant,
+    config: RateLimitConfig,
+}
+
+impl TokenBucket {
+    fn tokens_at(&self, now: Instant) -> f64 {
+        let elapsed = now.duration_since(self.last_refill).as_secs_f64();
+        (self.tokens + elapsed * self.config.tokens_per_second())
+            .min(f64::from(self.config.burst.get()))
+    }
+}
+
+/// Result of taking a token from a client's bucket.
+#[derive(Debug, Clone, Copy)]
+struct RateLimitState {
+    allowed: bool,
+    limit: u32,
+    remaining: u32,
+    /// Seconds until the bucket is full again.
+    reset: u64,
+    /// Seconds until the next request is allowed.
+    retry_after: u64,
+}
+
+#[derive(Debug, Default)]
+struct RateLimiter {
+    buckets: Mutex<HashMap<String, TokenBucket>>,
+    requests: AtomicU64,
+}
+
+impl RateLimiter {
+    fn take(&self, client: &str, config: RateLimitConfig) -> RateLimitState {
+        let now = Instant::now();
+        let burst = f64::from(config.burst.get());
+        let rate = config.tokens_per_second();
+
+        let mut buckets = self.buckets.lock().expect("rate limiter lock poisoned");
+        self.prune_if_needed(&mut buckets, now);
+
+        let bucket = buckets.entry(client.to_string()).or_insert(TokenBucket {
+            tokens: burst,
+            last_refill: now,
+            config,
+        });
+        // The limits of an API key may have changed since the bucket was created
+        bucket.config = config;
+        bucket.tokens = bucket.tokens_at(now);
+        bucket.last_refill = now;
+
+        let allowed = bucket.tokens >= 1.0;
+        if allowed {
+            bucket.tokens -= 1.0;
+        }
+
+        RateLimitState {
+            allowed,
+            limit: config.burst.get(),
+            remaining: bucket.tokens.floor() as u32,
+            reset: ((burst - bucket.tokens) / rate).ceil() as u64,
+            retry_after: ((1.0 - bucket.tokens).max(0.0) / rate).ceil() as u64,
+        }
+    }
+
+    /// Removes the buckets that would be full by now, as they are no different
+    /// from newly created ones.
+    fn prune_if_needed(&self, buckets: &mut HashMap<String, TokenBucket>, now: Instant) {
+        let requests = self.requests.fetch_add(1, Ordering::Relaxed) + 1;
+        if !requests.is_multiple_of(PRUNE_INTERVAL) {
+            return;
+        }
+
+        buckets.retain(|_, bucket| bucket.tokens_at(now) < f64::from(bucket.config.burst.get()));
+    }
+}
+
+/// Returns the address of the client, as reported by the reverse proxy, or
+/// `None` if the request didn't come through one.
+///
+/// `X-Real-IP` is preferred, as it is overwritten by the proxy. Otherwise, the
+/// last entry of `X-Forwarded-For` is used, since it's the one added by the
+/// proxy closest to the backend.
+fn client_address(headers: &HeaderMap) -> Option<String> {
+    let header_str = |name| {
+        headers
+            .get(name)
+            .and_then(|value: &HeaderValue| value.to_str().ok())
+    };
+
+    header_str(X_REAL_IP)
+        .map(str::trim)
+        .or_else(|| {
+            header_str(X_FORWARDED_FOR)
+                .and_then(|forwarded_for| forwarded_for.rsplit(',').next())
+                .map(str::trim)
+        })
+        .filter(|address| !address.is_empty())
+        .map(str::to_string)
+}
+
+/// Middleware limiting the number of API requests per client using a token
+/// bucket algorithm.
+///
+/// Requests with a valid API key in the `X-API-Key` header are limited
+/// according to the key's settings instead of the client's address, and count
+/// towards the key's daily quota. Anonymous requests are passed through if
+/// there is no anonymous limit configured, and rejected if the proxy headers
+/// are trusted but missing.
+#[derive(Debug, Clone)]
+pub struct RateLimitMiddleware {
+    limiter: Arc<RateLimiter>,
+    anonymous: Option<AnonymousRateLimit>,
+    api_keys: Option<Arc<ApiKeyStore>>,
+}
+
+impl RateLimitMiddleware {
+    #[must_use]
+    pub fn new(anonymous: Option<AnonymousRateLimit>, api_keys: Option<Arc<ApiKeyStore>>) -> Self {
+        Self {
+            limiter: Arc::new(RateLimiter::default()),
+            anonymous,
+            api_keys,
+        }
+    }
+}
+
+impl<S> Layer<S> for RateLimitMiddleware {
+    type Service = RateLimitService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        RateLimitService {
+            middleware: self.clone(),
+            inner,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RateLimitService<S> {
+    middleware: RateLimitMiddleware,
+    inner: S,
+}
+
+/// How a request is rate limited.
+enum Limit {
+    /// Anonymous request, limited in the bucket with the given key.
+    Anonymous(RateLimitConfig, String),
+    ApiKey(Arc<ApiKey>),
+}
+
+impl<S> RateLimitService<S> {
+    /// Returns the limit applying to a request, or the status and reason it
+    /// was rejected with.
+    fn limit_for(&self, headers: &HeaderMap) -> Result<Option<Limit>, (StatusCode, &'static str)> {
+        let Some(secret) = headers.get(API_KEY_HEADER) else {
+            return self
+                .middleware
+                .anonymous
+                .map(|anonymous| anonymous_limit(anonymous, headers))
+                .transpose();
+        };
+
+        let Some(api_keys) = &self.middleware.api_keys else {
+            return Err((
+                StatusCode::UNAUTHORIZED,
+                "API keys are not enabled on this server",
+            ));
+        };
+        secret
+            .to_str()
+            .ok()
+            .and_then(|secret| api_keys.authenticate(secret.trim()))
+            .map(|key| Some(Limit::ApiKey(key)))
+            .ok_or((StatusCode::UNAUTHORIZED, "Invalid API key"))
+    }
+}
+
+fn anonymous_limit(
+    anonymous: AnonymousRateLimit,
+    headers: &HeaderMap,
+) -> Result<Limit, (StatusCode, &'static str)> {
+    if !anonymous.trust_proxy_headers {
+        return Ok(Limit::Anonymous(
+            anonymous.config,
+            SHARED_ANONYMOUS_BUCKET.to_string(),
+        ));
+    }
+
+    client_address(headers)
+        .map(|address| Limit::Anonymous(anonymous.config, format!("ip:{address}")))
+        .ok_or((
+            StatusCode::FORBIDDEN,
+            "Requests without an API key have to come through the reverse proxy",
+        ))
+}
+
+impl<ReqBody, S> Service<Request<ReqBody>> for RateLimitService<S>
+where
+    S: Service<Request<ReqBody>, Response = Response>,
+    S::Future: Send + 'static,
+    S::Error: Send + 'static,
+{
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Response, S::Error>> + Send>>;
+    type Response = Response;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut request: Request<ReqBody>) -> Self::Future {
+        if !request.uri().path().starts_with(RATE_LIMITED_PATH_PREFIX) {
+            return Box::pin(self.inner.call(request));
+        }
+        let request_id = request.extensions().get::<RequestId>().cloned();
+        let limit = match self.limit_for(request.headers()) {
+            Ok(Some(limit)) => limit,
+            Ok(None) => return Box::pin(self.inner.call(request)),
+            Err((status, message)) => {
+                let response = error_response(status, message.to_string(), request_id.as_ref());
+                return Box::pin(std::future::ready(Ok(response)));
+            }
+        };
+
+        let limiter = &self.middleware.limiter;
+        let state = match &limit {
+            Limit::Anonymous(config, client) => limiter.take(client, *config),
+            Limit::ApiKey(key) => limiter.take(
+                &format!("api-key:{}", key.name),
+                RateLimitConfig {
+                    requests_per_minute: key.requests_per_minute,
+                    burst: key.burst,
+                },
+            ),
+        };
+        if !state.allowed {
+            let mut response = too_many_requests_response(
+                format!(
+                    "Too many requests, please try again in {} seconds",
+                    state.retry_after
+                ),
+                state.retry_after,
+                request_id.as_ref(),
+            );
+            add_rate_limit_headers(response.headers_mut(), state);
+            return Box::pin(std::future::ready(Ok(response)));
+        }
+
+        if let Limit::ApiKey(key) = limit {
+            let api_keys = self
+                .middleware
+                .api_keys
+                .as_ref()
+                .expect("API key authenticated without a key store");
+            match api_keys.record_request(&key) {
+                QuotaState::Allowed(usage) => {
+                    request
+                        .extensions_mut()
+                        .insert(AuthenticatedApiKey { key, usage });
+                }
+                QuotaState::Exceeded(retry_after) => {
+                    let quota = key.daily_quota.map_or(0, NonZeroU64::get);
+                    let response = too_many_requests_response(
+                        format!("Daily quota of {quota} requests exceeded"),
+                        retry_after,
+                        request_id.as_ref(),
+                    );
+                    return Box::pin(std::future::ready(Ok(response)));
+                }
+            }
+        }
+
+        let future = self.inner.call(request);
+        Box::pin(async move {
+            let mut response = future.await?;
+            add_rate_limit_headers(response.headers_mut(), state);
+            Ok(response)
+        })
+    }
+}
+
+fn error_response(status: StatusCode, message: String, request_id: Option<&RequestId>) -> Response {
+    Json(ServiceErrorResponse {
+        message,
+        request_id: request_id.map(RequestId::to_string),
+    })
+    .with_status(status)
+    .into_response()
+    .unwrap_or_else(|error| {
+        error!("Could not create error response: {error:?}");
+        let mut response = Response::default();
+        *response.status_mut() = status;
+        response
+    })
+}
+
+fn too_many_requests_response(
+    message: String,
+    retry_after: u64,
+    request_id: Option<&RequestId>,
+) -> Response {
+    let mut response = error_response(StatusCode::TOO_MANY_REQUESTS, message, request_id);
+    response
+        .headers_mut()
+        .insert(RETRY_AFTER, HeaderValue::from(retry_after));
+    response
+}
+
+fn add_rate_limit_headers(headers: &mut HeaderMap, state: RateLimitState) {
+    headers.insert(RATE_LIMIT_LIMIT, HeaderValue::from(state.limit));
+    headers.insert(RATE_LIMIT_REMAINING, HeaderValue::from(state.remaining));
+    headers.insert(RATE_LIMIT_RESET, HeaderValue::from(state.reset));
+}
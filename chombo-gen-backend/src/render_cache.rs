@@ -0,0 +1,64 @@
+use std::sync::{LazyLock, Mutex};
+
+use bytes::Bytes;
+use chombo_gen_common::tile_set::TileSet;
+use chombo_gen_render::tile_theme::TileStyle;
+use indexmap::IndexMap;
+
+use crate::config::config;
+
+pub static RENDER_CACHE: LazyLock<RenderCache> =
+    LazyLock::new(|| RenderCache::new(config().cache.render_cache_capacity));
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct RenderCacheKey {
+    /// Canonical notation of the hand, as returned by
+    /// [`normalize`](chombo_gen_render::normalize::normalize).
+    pub hand: String,
+    pub tile_set: TileSet,
+    pub style: TileStyle,
+}
+
+/// In-memory cache of rendered images, evicting the least recently used entry
+/// once the capacity is exceeded.
+#[derive(Debug)]
+pub struct RenderCache {
+    entries: Mutex<IndexMap<RenderCacheKey, Bytes>>,
+    capacity: usize,
+}
+
+impl RenderCache {
+    #[must_use]
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            entries: Mutex::new(IndexMap::with_capacity(capacity)),
+            capacity,
+        }
+    }
+
+    pub fn get(&self, key: &RenderCacheKey) -> Option<Bytes> {
+        let mut entries = self.entries.lock().expect("render cache lock poisoned");
+        let index = entries.get_index_of(key)?;
+        let last_index = entries.len() - 1;
+        entries.move_index(index, last_index);
+        entries
+            .get_index(last_index)
+            .map(|(_, image)| image.clone())
+    }
+
+    pub fn insert(&self, key: RenderCacheKey, image: Bytes) {
+        let mut entries = self.entries.lock().expect("render cache lock poisoned");
+        entries.shift_remove(&key);
+        entries.insert(key, image);
+        while entries.len() > self.capacity {
+            entries.shift_remove_index(0);
+        }
+    }
+
+    pub fn remove_tile_set(&self, tile_set: &TileSet) {
+        self.entries
+            .lock()
+            .expect("render cache lock poisoned")
+            .retain(|key, _| &key.tile_set != tile_set);
+    }
+}
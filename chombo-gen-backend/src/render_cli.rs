@@ -0,0 +1,341 @@
+use std::collections::HashSet;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use async_trait::async_trait;
+use chombo_gen_common::hand::HandWarning;
+use chombo_gen_common::tile_set::TileSet;
+use chombo_gen_render::OutputFormat;
+use chombo_gen_render::cli::{format_arg, output_format, strict, style_args, tile_style};
+use chombo_gen_render::latex::LatexOptions;
+use chombo_gen_render::lint::warnings_summary;
+use chombo_gen_render::tile_theme::TileStyle;
+use cot::cli::CliTask;
+use cot::cli::clap::{Arg, ArgMatches, Command, value_parser};
+use cot::project::{Bootstrapper, WithConfig};
+
+use crate::config::config;
+use crate::errors::ServiceError;
+use crate::load_tile_sets;
+use crate::render_hand::render_request;
+use crate::tile_set_registry::TILE_SETS;
+
+const HANDS_PARAM: &str = "hands";
+const INPUT_PARAM: &str = "input";
+const OUTPUT_DIR_PARAM: &str = "output-dir";
+const NAME_PARAM: &str = "name";
+const TILE_SET_PARAM: &str = "tile-set";
+const TILE_WIDTH_PARAM: &str = "tile-width";
+const ASSETS_PATH_PARAM: &str = "assets-path";
+
+const LATEX_FORMAT: &str = "latex";
+
+const STDIN_PATH: &str = "-";
+/// Directory in the output directory holding the tile images used by the
+/// LaTeX snippets.
+const LATEX_TILES_DIR: &str = "tiles";
+
+/// Format of the files written by the command.
+#[derive(Debug, Clone)]
+enum FileFormat {
+    Image(OutputFormat),
+    /// TikZ pictures, with the tile images in `assets_dir`.
+    Latex {
+        options: LatexOptions,
+        assets_dir: PathBuf,
+    },
+}
+
+impl FileFormat {
+    fn extension(&self) -> &'static str {
+        match self {
+            Self::Image(format) => format.extension(),
+            Self::Latex { .. } => "tex",
+        }
+    }
+}
+
+/// `render` subcommand rendering hands to image files, without running the
+/// server.
+pub struct RenderTask;
+
+#[async_trait(?Send)]
+impl CliTask for RenderTask {
+    fn subcommand(&self) -> Command {
+        Command::new("render")
+            .about("Render hands to image files")
+            .arg(
+                Arg::new(HANDS_PARAM)
+                    .help(
+                        "Hands to render; if none are given, they are read from --input or the \
+                         standard input",
+                    )
+                    .num_args(0..),
+            )
+            .arg(
+                Arg::new(INPUT_PARAM)
+                    .help(
+                        "File with one hand per line (empty lines and lines starting with `#` \
+                         are skipped), or `-` for the standard input",
+                    )
+                    .short('i')
+                    .long(INPUT_PARAM)
+                    .conflicts_with(HANDS_PARAM)
+                    .value_parser(value_parser!(PathBuf)),
+            )
+            .arg(
+                Arg::new(OUTPUT_DIR_PARAM)
+                    .help("Directory to write the images to")
+                    .short('o')
+                    .long(OUTPUT_DIR_PARAM)
+                    .default_value(".")
+                    .value_parser(value_parser!(PathBuf)),
+            )
+            .arg(
+                Arg::new(NAME_PARAM)
+                    .help(
+                        "Name of the image files, without the extension; `{hand}` is replaced \
+                         with the hand, `{index}` with its position in the input (starting at \
+                         1) and `{tile_set}` with the tile set",
+                    )
+                    .long(NAME_PARAM)
+                    .default_value("{hand}"),
+            )
+            .arg(
+                Arg::new(TILE_SET_PARAM)
+                    .help("Tile set to render the hands with")
+                    .short('t')
+                    .long(TILE_SET_PARAM)
+                    .default_value("Yellow"),
+            )
+            .arg(format_arg(&[LATEX_FORMAT]))
+            .args(style_args())
+            .arg(
+                Arg::new(TILE_WIDTH_PARAM)
+                    .help("Width of a tile in the LaTeX output, in millimetres")
+                    .long(TILE_WIDTH_PARAM)
+                    .default_value("8")
+                    .value_parser(value_parser!(f64)),
+            )
+            .arg(
+                Arg::new(ASSETS_PATH_PARAM)
+                    .help(
+                        "Path of the tile images in the LaTeX output, as seen from the directory \
+                         the document is compiled in; the `tiles` subdirectory of the output \
+                         directory by default",
+                    )
+                    .long(ASSETS_PATH_PARAM),
+            )
+    }
+
+    async fn execute(
+        &mut self,
+        matches: &ArgMatches,
+        _bootstrapper: Bootstrapper<WithConfig>,
+    ) -> cot::Result<()> {
+        load_tile_sets()?;
+
+        let tile_set = TileSet::new(
+            matches
+                .get_one::<String>(TILE_SET_PARAM)
+                .expect("default provided")
+                .as_str(),
+        );
+        let style = tile_style(matches).map_err(cot::Error::internal)?;
+        let strict = strict(matches);
+        let name_template = matches
+            .get_one::<String>(NAME_PARAM)
+            .expect("default provided");
+        let output_dir = matches
+            .get_one::<PathBuf>(OUTPUT_DIR_PARAM)
+            .expect("default provided");
+        let format = file_format(matches, output_dir)?;
+
+        let hands = read_hands(matches)?;
+        if hands.is_empty() {
+            return Err(cot::Error::internal("no hands to render"));
+        }
+
+        let mut paths = HashSet::new();
+        let outputs: Vec<_> = hands
+            .iter()
+            .enumerate()
+            .map(|(index, hand)| {
+                let name = output_name(name_template, index + 1, hand, &tile_set);
+                output_dir.join(format!("{name}.{}", format.extension()))
+            })
+            .collect();
+        if let Some(path) = outputs.iter().find(|path| !paths.insert(*path)) {
+            return Err(cot::Error::internal(format!(
+                "{} would be written more than once; use {{index}} in --{NAME_PARAM}",
+                path.display()
+            )));
+        }
+        std::fs::create_dir_all(output_dir).map_err(|error| {
+            cot::Error::internal(format!(
+                "could not create {}: {error}",
+                output_dir.display()
+            ))
+        })?;
+
+        let mut written_assets = HashSet::new();
+        let mut failed = 0;
+        for (hand, path) in hands.iter().zip(&outputs) {
+            match render_to_file(
+                hand,
+                &tile_set,
+                style,
+                strict,
+                &format,
+                path,
+                &mut written_assets,
+            ) {
+                Ok(warnings) => {
+                    if !warnings.is_empty() {
+                        eprintln!("warning: {hand}: {}", warnings_summary(&warnings));
+                    }
+                    println!("{}", path.display());
+                }
+                Err(error) => {
+                    eprintln!("error: {hand}: {error}");
+                    failed += 1;
+                }
+            }
+        }
+
+        if failed > 0 {
+            return Err(cot::Error::internal(format!(
+                "{failed} of {} hands could not be rendered",
+                hands.len()
+            )));
+        }
+        Ok(())
+    }
+}
+
+fn file_format(matches: &ArgMatches, output_dir: &Path) -> cot::Result<FileFormat> {
+    // LaTeX is the only format that is not an image format
+    if let Some(format) = output_format(matches) {
+        return Ok(FileFormat::Image(format));
+    }
+
+    let tile_width_mm = *matches
+        .get_one::<f64>(TILE_WIDTH_PARAM)
+        .expect("default provided");
+    if !(tile_width_mm.is_finite() && tile_width_mm > 0.0) {
+        return Err(cot::Error::internal(format!(
+            "--{TILE_WIDTH_PARAM} must be a positive number"
+        )));
+    }
+    let assets_dir = output_dir.join(LATEX_TILES_DIR);
+    let assets_path = matches
+        .get_one::<String>(ASSETS_PATH_PARAM)
+        .cloned()
+        .unwrap_or_else(|| assets_dir.to_string_lossy().replace('\\', "/"));
+    Ok(FileFormat::Latex {
+        options: LatexOptions {
+            tile_width_mm,
+            assets_path,
+        },
+        assets_dir,
+    })
+}
+
+/// Renders a hand the same way as the API does, and writes it to `path`. The
+/// tile images of LaTeX snippets are only written once, the first time a
+/// tile is used.
+fn render_to_file(
+    hand: &str,
+    tile_set: &TileSet,
+    style: TileStyle,
+    strict: bool,
+    format: &FileFormat,
+    path: &Path,
+    written_assets: &mut HashSet<String>,
+) -> Result<Vec<HandWarning>, ServiceError> {
+    let hand_obj = config().limits.render_limits().parse_hand(hand)?;
+    let tile_set = TILE_SETS.get(tile_set)?.load_blocking()?;
+
+    match format {
+        FileFormat::Image(format) => {
+            let rendered = render_request(hand_obj, style, *format, strict).render(&tile_set)?;
+            write_file(path, &rendered.data)?;
+            Ok(rendered.warnings)
+        }
+        FileFormat::Latex {
+            options,
+            assets_dir,
+        } => {
+            let exported = render_request(hand_obj, style, OutputFormat::Png, strict)
+                .render_latex(&tile_set, options)?;
+            for asset in exported.assets {
+                if !written_assets.contains(&asset.file_name) {
+                    std::fs::create_dir_all(assets_dir).map_err(|error| {
+                        ServiceError::Internal(format!(
+                            "could not create {}: {error}",
+                            assets_dir.display()
+                        ))
+                    })?;
+                    write_file(&assets_dir.join(&asset.file_name), &asset.data)?;
+                    written_assets.insert(asset.file_name);
+                }
+            }
+            write_file(path, exported.snippet.as_bytes())?;
+            Ok(exported.warnings)
+        }
+    }
+}
+
+fn write_file(path: &Path, data: &[u8]) -> Result<(), ServiceError> {
+    std::fs::write(path, data).map_err(|error| {
+        ServiceError::Internal(format!("could not write {}: {error}", path.display()))
+    })
+}
+
+fn read_hands(matches: &ArgMatches) -> cot::Result<Vec<String>> {
+    if let Some(hands) = matches.get_many::<String>(HANDS_PARAM) {
+        return Ok(hands.cloned().collect());
+    }
+
+    let input = matches
+        .get_one::<PathBuf>(INPUT_PARAM)
+        .map_or(Path::new(STDIN_PATH), PathBuf::as_path);
+    let content = if input == Path::new(STDIN_PATH) {
+        let mut content = String::new();
+        std::io::stdin()
+            .read_to_string(&mut content)
+            .map(|_| content)
+    } else {
+        std::fs::read_to_string(input)
+    }
+    .map_err(|error| {
+        cot::Error::internal(format!("could not read {}: {error}", input.display()))
+    })?;
+
+    Ok(content
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(ToString::to_string)
+        .collect())
+}
+
+/// Returns the name of the image file of a hand, with the characters of the
+/// hand that are not safe in file names replaced.
+fn output_name(template: &str, index: usize, hand: &str, tile_set: &TileSet) -> String {
+    let hand: String = hand
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '_' || c == '-' {
+                c
+            } else {
+                '-'
+            }
+        })
+        .collect();
+
+    template
+        .replace("{hand}", &hand)
+        .replace("{index}", &index.to_string())
+        .replace("{tile_set}", tile_set.id())
+}
@@ -1,27 +1,39 @@
-use std::io::Cursor;
-use std::time::Instant;
+use std::marker::PhantomData;
+use std::time::{Duration, Instant};
 
 use bytes::Bytes;
+use chombo_gen_common::hand::{HandWarning, ParsedHand};
 use chombo_gen_common::tile_set::TileSet;
+use chombo_gen_render::lint::warnings_summary;
+use chombo_gen_render::normalize::normalize;
+use chombo_gen_render::tile_theme::TileStyle;
+use chombo_gen_render::{OutputFormat, RenderError, RenderRequest, RenderedImage};
 use cot::aide::openapi::Operation;
+use cot::http::header::{CACHE_CONTROL, CONTENT_DISPOSITION, CONTENT_TYPE};
+use cot::http::{HeaderName, HeaderValue};
+use cot::json::Json;
 use cot::openapi::{ApiOperationResponse, RouteContext};
 use cot::request::extractors::UrlQuery;
 use cot::response::{IntoResponse, Response};
 use cot::{Body, StatusCode};
-use image::{DynamicImage, ImageError};
+use image::ImageError;
 use indexmap::IndexMap;
-use riichi_hand::parser::{HandParseError, HandParser};
-use riichi_hand::raster_renderer::fluffy_stuff_tile_sets::{
-    BLACK_FLUFFY_STUFF_TILE_SET, RED_FLUFFY_STUFF_TILE_SET, YELLOW_FLUFFY_STUFF_TILE_SET,
-};
-use riichi_hand::raster_renderer::martin_persson_tile_sets::MARTIN_PERSSON_TILE_SET;
-use riichi_hand::raster_renderer::{HandRenderError, RasterRenderer, RenderOptions};
+use riichi_hand::Hand;
+use riichi_hand::parser::HandParseError;
+use riichi_hand::raster_renderer::HandRenderError;
 use schemars::{JsonSchema, SchemaGenerator};
 use serde::Deserialize;
-use time::Duration;
-use tracing::{error, info};
+use tracing::field::Empty;
+use tracing::{Span, error, info};
 
+use crate::config::config;
 use crate::errors::ServiceError;
+use crate::metrics::METRICS;
+use crate::parse_hand::from_parsed_hand;
+use crate::render_cache::{RENDER_CACHE, RenderCacheKey};
+use crate::render_limiter::render_limiter;
+use crate::server_timing::{SERVER_TIMING_HEADER, ServerTiming, millis};
+use crate::tile_set_registry::TILE_SETS;
 
 impl From<HandParseError> for ServiceError {
     fn from(error: HandParseError) -> Self {
@@ -44,89 +56,297 @@ impl From<HandRenderError> for ServiceError {
     }
 }
 
-const MAX_HAND_LEN: usize = 100;
-const CACHE_MAX_AGE: Duration = Duration::days(7);
+impl From<RenderError> for ServiceError {
+    fn from(error: RenderError) -> Self {
+        match error {
+            RenderError::Parse(error) => error.into(),
+            RenderError::Render(error) => error.into(),
+            RenderError::Encode(error) => error.into(),
+            error => Self::BadRequest(error.to_string()),
+        }
+    }
+}
+
+pub const HAND_WARNINGS_HEADER: HeaderName = HeaderName::from_static("x-hand-warnings");
 
 #[derive(Debug, Clone, Deserialize, JsonSchema)]
 pub struct RenderHandParams {
     hand: String,
     tile_set: TileSet,
+    #[serde(flatten)]
+    style: TileStyle,
+    #[serde(default)]
+    strict: bool,
 }
 
+pub fn validate_hand_len(hand: &str) -> Result<(), ServiceError> {
+    Ok(config().limits.render_limits().check_hand_len(hand)?)
+}
+
+#[tracing::instrument(name = "render_request", skip_all, fields(parse_ms = Empty))]
 pub async fn new_render_hand(
-    UrlQuery(RenderHandParams { hand, tile_set }): UrlQuery<RenderHandParams>,
+    UrlQuery(RenderHandParams {
+        hand,
+        tile_set,
+        style,
+        strict,
+    }): UrlQuery<RenderHandParams>,
 ) -> Result<ImageResponse, ServiceError> {
-    if hand.len() > MAX_HAND_LEN {
-        return Err(ServiceError::BadRequest(format!(
-            "Maximum hand description length exceeded ({}/{} characters)",
-            hand.len(),
-            MAX_HAND_LEN
-        )));
+    let result: Result<_, ServiceError> = async {
+        let parse_time = Instant::now();
+        let hand_obj = config().limits.render_limits().parse_hand(&hand)?;
+        let parse_elapsed = record_parse_time(parse_time);
+
+        Ok(render_hand(hand_obj, tile_set.clone(), style, strict)
+            .await?
+            .with_timing("parse", parse_elapsed))
     }
+    .await;
+    record_render_request(&tile_set, result)
+}
 
-    let hand_obj = HandParser::parse(&hand)?;
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+pub struct RenderParsedHandRequest {
+    hand: ParsedHand,
+    tile_set: TileSet,
+    #[serde(flatten)]
+    style: TileStyle,
+    #[serde(default)]
+    strict: bool,
+}
 
-    let buf = tokio::task::spawn_blocking(move || {
-        let render_time = Instant::now();
-        let options = RenderOptions::default();
-        let image = match tile_set {
-            TileSet::Yellow => {
-                RasterRenderer::render(&hand_obj, &*YELLOW_FLUFFY_STUFF_TILE_SET, options)
-            }
-            TileSet::Red => RasterRenderer::render(&hand_obj, &*RED_FLUFFY_STUFF_TILE_SET, options),
-            TileSet::Black => {
-                RasterRenderer::render(&hand_obj, &*BLACK_FLUFFY_STUFF_TILE_SET, options)
-            }
-            TileSet::MartinPersson => {
-                RasterRenderer::render(&hand_obj, &*MARTIN_PERSSON_TILE_SET, options)
-            }
-        }?;
-        let render_elapsed = render_time.elapsed();
+#[tracing::instrument(name = "render_request", skip_all, fields(parse_ms = Empty))]
+pub async fn render_parsed_hand(
+    Json(RenderParsedHandRequest {
+        hand,
+        tile_set,
+        style,
+        strict,
+    }): Json<RenderParsedHandRequest>,
+) -> Result<ImageResponse, ServiceError> {
+    let result: Result<_, ServiceError> = async {
+        let parse_time = Instant::now();
+        let hand_obj = from_parsed_hand(&hand, "hand")?;
+        validate_hand_len(&normalize(&hand_obj, false))?;
+        let parse_elapsed = record_parse_time(parse_time);
+
+        Ok(render_hand(hand_obj, tile_set.clone(), style, strict)
+            .await?
+            .with_timing("parse", parse_elapsed))
+    }
+    .await;
+    record_render_request(&tile_set, result)
+}
+
+fn record_parse_time(parse_time: Instant) -> Duration {
+    let parse_elapsed = parse_time.elapsed();
+    Span::current().record("parse_ms", millis(parse_elapsed));
+    parse_elapsed
+}
+
+/// Counts a render request in the metrics, by tile set and response status.
+pub fn record_render_request<T>(
+    tile_set: &TileSet,
+    result: Result<T, ServiceError>,
+) -> Result<T, ServiceError> {
+    let status = result
+        .as_ref()
+        .map_or_else(ServiceError::get_http_status, |_| StatusCode::OK);
+    METRICS.record_render_request(tile_set, status);
+    result
+}
+
+#[tracing::instrument(
+    skip_all,
+    fields(
+        %tile_set,
+        hand = Empty,
+        cache_hit = Empty,
+        queue_wait_ms = Empty,
+        queue_depth = Empty,
+        render_ms = Empty,
+        encode_ms = Empty,
+    )
+)]
+pub async fn render_hand(
+    hand_obj: Hand,
+    tile_set: TileSet,
+    style: TileStyle,
+    strict: bool,
+) -> Result<ImageResponse, ServiceError> {
+    let loaded_tile_set = TILE_SETS.get(&tile_set)?.load().await?;
+    let request = render_request(hand_obj, style, OutputFormat::Png, strict);
+    let warnings = request.check(&loaded_tile_set)?;
 
-        let create_png_time = Instant::now();
-        let mut buf = Vec::new();
-        DynamicImage::ImageRgba8(image)
-            .write_to(&mut Cursor::new(&mut buf), image::ImageFormat::Png)?;
-        let create_png_elapsed = create_png_time.elapsed();
+    let cache_key = RenderCacheKey {
+        hand: normalize(&request.hand, false),
+        tile_set,
+        style,
+    };
+    let span = Span::current();
+    span.record("hand", cache_key.hand.as_str());
+    let cache_time = Instant::now();
+    let cached_image = RENDER_CACHE.get(&cache_key);
+    span.record("cache_hit", cached_image.is_some());
+    if let Some(image) = cached_image {
+        return Ok(ImageResponse::new(image)
+            .with_warnings(&warnings)
+            .with_timing("cache", cache_time.elapsed()));
+    }
+
+    let permit = render_limiter().acquire().await?;
+    METRICS.record_queue_wait(permit.wait_time);
+    span.record("queue_wait_ms", millis(permit.wait_time));
+    span.record("queue_depth", permit.queue_depth);
+    let queue_wait = permit.wait_time;
+    let tile_set_id = cache_key.tile_set.clone();
+    let rendered: RenderedImage = tokio::task::spawn_blocking(move || {
+        let _entered = span.enter();
+        let rendered = request.render_checked(&loaded_tile_set, warnings)?;
 
-        info!(
-            "Hand {hand}: rendering took {render_elapsed:?}, PNG encoding took {create_png_elapsed:?}"
+        drop(permit);
+        span.record("render_ms", millis(rendered.render_time));
+        span.record("encode_ms", millis(rendered.encode_time));
+        info!("Rendered hand");
+        METRICS.record_render(
+            &tile_set_id,
+            rendered.render_time,
+            rendered.encode_time,
+            rendered.data.len(),
         );
 
-        Ok::<Vec<u8>, ServiceError>(buf)
+        Ok::<_, ServiceError>(rendered)
     })
     .await??;
 
-    Ok(ImageResponse::new(buf))
+    let image = Bytes::from(rendered.data);
+    RENDER_CACHE.insert(cache_key, image.clone());
+
+    Ok(ImageResponse::new(image)
+        .with_warnings(&rendered.warnings)
+        .with_timing("queue", queue_wait)
+        .with_timing("render", rendered.render_time)
+        .with_timing("encode", rendered.encode_time))
+}
+
+/// Creates a request rendering a hand with the configured limits.
+#[must_use]
+pub fn render_request(
+    hand_obj: Hand,
+    style: TileStyle,
+    format: OutputFormat,
+    strict: bool,
+) -> RenderRequest {
+    RenderRequest {
+        style,
+        format,
+        strict,
+        limits: config().limits.render_limits(),
+        ..RenderRequest::new(hand_obj)
+    }
+}
+
+/// Type of the content of an [`ImageResponse`].
+pub trait ResponseContent {
+    const MIME_TYPE: &'static str;
+    /// Description of the response in the API documentation.
+    const DESCRIPTION: &'static str;
+}
+
+/// PNG image of a hand.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PngImage;
+
+impl ResponseContent for PngImage {
+    const MIME_TYPE: &'static str = "image/png";
+    const DESCRIPTION: &'static str = "Image";
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
-pub struct ImageResponse {
-    image: Bytes,
+pub struct ImageResponse<C = PngImage> {
+    data: Bytes,
+    /// Name of the file the content is saved as, if any.
+    file_name: Option<&'static str>,
+    warnings: Option<String>,
+    timing: ServerTiming,
+    content: PhantomData<C>,
 }
 
-impl ImageResponse {
+impl<C> ImageResponse<C> {
     #[must_use]
-    pub fn new(image: Vec<u8>) -> Self {
+    pub fn new(data: Bytes) -> Self {
         Self {
-            image: Bytes::from(image),
+            data,
+            file_name: None,
+            warnings: None,
+            timing: ServerTiming::default(),
+            content: PhantomData,
         }
     }
+
+    #[must_use]
+    pub fn with_file_name(mut self, file_name: &'static str) -> Self {
+        self.file_name = Some(file_name);
+        self
+    }
+
+    /// Sends the warnings in the `X-Hand-Warnings` header; characters that
+    /// can't be sent in headers, outside of printable ASCII, are replaced
+    /// with `?`.
+    #[must_use]
+    pub fn with_warnings(mut self, warnings: &[HandWarning]) -> Self {
+        if !warnings.is_empty() {
+            let summary = warnings_summary(warnings)
+                .chars()
+                .map(|c| if matches!(c, ' '..='~') { c } else { '?' })
+                .collect();
+            self.warnings = Some(summary);
+        }
+        self
+    }
+
+    #[must_use]
+    pub fn with_timing(mut self, name: &'static str, duration: Duration) -> Self {
+        self.timing.add(name, duration);
+        self
+    }
 }
 
-impl IntoResponse for ImageResponse {
+impl<C: ResponseContent> IntoResponse for ImageResponse<C> {
     fn into_response(self) -> cot::Result<Response> {
-        Response::new(Body::fixed(self.image))
-            .with_header(cot::http::header::CONTENT_TYPE, "image/png")
+        let mut response = Response::new(Body::fixed(self.data))
+            .with_header(CONTENT_TYPE, C::MIME_TYPE)
             .with_header(
-                cot::http::header::CACHE_CONTROL,
-                format!("public, max-age={}", CACHE_MAX_AGE.whole_seconds()),
+                CACHE_CONTROL,
+                format!("public, max-age={}", config().cache.image_max_age.as_secs()),
             )
-            .into_response()
+            .into_response()?;
+        if let Some(file_name) = self.file_name
+            && let Ok(content_disposition) =
+                HeaderValue::try_from(format!("inline; filename=\"{file_name}\""))
+        {
+            response
+                .headers_mut()
+                .insert(CONTENT_DISPOSITION, content_disposition);
+        }
+        if let Some(warnings) = self.warnings {
+            match HeaderValue::try_from(warnings) {
+                Ok(warnings) => {
+                    response
+                        .headers_mut()
+                        .insert(HAND_WARNINGS_HEADER, warnings);
+                }
+                Err(error) => error!("Could not send the hand warnings header: {error}"),
+            }
+        }
+        if let Some(timing) = self.timing.header_value() {
+            response.headers_mut().insert(SERVER_TIMING_HEADER, timing);
+        }
+        Ok(response)
     }
 }
 
-impl ApiOperationResponse for ImageResponse {
+impl<C: ResponseContent> ApiOperationResponse for ImageResponse<C> {
     fn api_operation_responses(
         _operation: &mut Operation,
         _route_context: &RouteContext<'_>,
@@ -140,9 +360,9 @@ impl ApiOperationResponse for ImageResponse {
                 StatusCode::OK.as_u16(),
             )),
             cot::aide::openapi::Response {
-                description: "Image".to_string(),
+                description: C::DESCRIPTION.to_string(),
                 content: IndexMap::from([(
-                    "image/png".to_string(),
+                    C::MIME_TYPE.to_string(),
                     cot::aide::openapi::MediaType {
                         schema: None,
                         ..Default::default()
@@ -0,0 +1,138 @@
+use std::num::NonZeroUsize;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, OnceLock};
+use std::time::{Duration, Instant};
+
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+use crate::config::{LimitsConfig, config};
+use crate::env_config::env_var;
+use crate::errors::ServiceError;
+
+pub const MAX_CONCURRENT_RENDERS_ENV: &str = "CHOMBO_GEN_MAX_CONCURRENT_RENDERS";
+pub const MAX_QUEUED_RENDERS_ENV: &str = "CHOMBO_GEN_MAX_QUEUED_RENDERS";
+
+static RENDER_LIMITER: OnceLock<RenderLimiter> = OnceLock::new();
+
+/// Returns the limiter configured with [`init_render_limiter`], or one with
+/// the configured limits.
+pub fn render_limiter() -> &'static RenderLimiter {
+    RENDER_LIMITER.get_or_init(|| {
+        let limits = &config().limits;
+        RenderLimiter::new(
+            limits
+                .max_concurrent_renders
+                .map_or_else(default_max_concurrent_renders, NonZeroUsize::get),
+            limits.max_queued_renders.get(),
+        )
+    })
+}
+
+fn default_max_concurrent_renders() -> usize {
+    std::thread::available_parallelism().map_or(1, NonZeroUsize::get)
+}
+
+/// Configures the render limits from the config, overridden by the
+/// environment variables.
+pub fn init_render_limiter(limits: &LimitsConfig) -> cot::Result<&'static RenderLimiter> {
+    let max_concurrent = env_var::<NonZeroUsize>(MAX_CONCURRENT_RENDERS_ENV)?
+        .or(limits.max_concurrent_renders)
+        .map_or_else(default_max_concurrent_renders, NonZeroUsize::get);
+    let max_queued = env_var::<NonZeroUsize>(MAX_QUEUED_RENDERS_ENV)?
+        .unwrap_or(limits.max_queued_renders)
+        .get();
+
+    Ok(RENDER_LIMITER.get_or_init(|| RenderLimiter::new(max_concurrent, max_queued)))
+}
+
+/// Limits the number of hands rendered at the same time, making at most
+/// `max_queued` requests wait for their turn and rejecting the rest.
+#[derive(Debug)]
+pub struct RenderLimiter {
+    semaphore: Arc<Semaphore>,
+    queued: AtomicUsize,
+    max_concurrent: usize,
+    max_queued: usize,
+}
+
+/// Permission to render a hand; the slot is released when this is dropped.
+#[derive(Debug)]
+pub struct RenderPermit {
+    _permit: OwnedSemaphorePermit,
+    /// Number of requests waiting for a render slot (including this one) when
+    /// this request started waiting.
+    pub queue_depth: usize,
+    pub wait_time: Duration,
+}
+
+impl RenderLimiter {
+    #[must_use]
+    fn new(max_concurrent: usize, max_queued: usize) -> Self {
+        Self {
+            semaphore: Arc::new(Semaphore::new(max_concurrent)),
+            queued: AtomicUsize::new(0),
+            max_concurrent,
+            max_queued,
+        }
+    }
+
+    #[must_use]
+    pub fn max_concurrent(&self) -> usize {
+        self.max_concurrent
+    }
+
+    #[must_use]
+    pub fn max_queued(&self) -> usize {
+        self.max_queued
+    }
+
+    /// Returns the number of requests waiting for a render slot.
+    #[must_use]
+    pub fn queued(&self) -> usize {
+        self.queued.load(Ordering::SeqCst)
+    }
+
+    /// Returns the number of hands being rendered.
+    #[must_use]
+    pub fn in_progress(&self) -> usize {
+        self.max_concurrent - self.semaphore.available_permits()
+    }
+
+    pub async fn acquire(&self) -> Result<RenderPermit, ServiceError> {
+        let wait_start = Instant::now();
+        if let Ok(permit) = Arc::clone(&self.semaphore).try_acquire_owned() {
+            return Ok(RenderPermit {
+                _permit: permit,
+                queue_depth: 0,
+                wait_time: wait_start.elapsed(),
+            });
+        }
+
+        let queue_depth = self.queued.fetch_add(1, Ordering::SeqCst) + 1;
+        // Leave the queue even if the request is cancelled while waiting
+        let _queue_guard = QueueGuard(&self.queued);
+        if queue_depth > self.max_queued {
+            return Err(ServiceError::ServiceUnavailable(
+                "Server is busy rendering other hands, please try again later".to_string(),
+            ));
+        }
+
+        let permit = Arc::clone(&self.semaphore)
+            .acquire_owned()
+            .await
+            .expect("render semaphore is never closed");
+        Ok(RenderPermit {
+            _permit: permit,
+            queue_depth,
+            wait_time: wait_start.elapsed(),
+        })
+    }
+}
+
+struct QueueGuard<'a>(&'a AtomicUsize);
+
+impl Drop for QueueGuard<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
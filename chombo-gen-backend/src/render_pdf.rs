@@ -0,0 +1,284 @@
+use std::time::{Duration, Instant};
+
+use bytes::Bytes;
+use chombo_gen_common::hand::HandWarning;
+use chombo_gen_common::tile_set::TileSet;
+use chombo_gen_render::OutputFormat;
+use chombo_gen_render::pdf::{PaperSize, PrintSheet, SheetHand};
+use chombo_gen_render::tile_theme::TileStyle;
+use cot::json::Json;
+use cot::request::extractors::UrlQuery;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use tracing::field::Empty;
+use tracing::{Span, info};
+
+use crate::config::config;
+use crate::errors::ServiceError;
+use crate::metrics::METRICS;
+use crate::render_hand::{ImageResponse, ResponseContent, record_render_request, render_request};
+use crate::render_limiter::render_limiter;
+use crate::server_timing::millis;
+use crate::tile_set_registry::TILE_SETS;
+
+const MAX_TITLE_LEN: usize = 200;
+const MAX_COLUMNS: usize = 6;
+const MAX_MARGIN_MM: f64 = 50.0;
+const MIN_TILE_WIDTH_MM: f64 = 1.0;
+const MAX_TILE_WIDTH_MM: f64 = 50.0;
+
+fn default_tile_width() -> f64 {
+    8.0
+}
+
+fn default_columns() -> usize {
+    2
+}
+
+fn default_margin() -> f64 {
+    15.0
+}
+
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+pub struct RenderHandPdfParams {
+    hand: String,
+    tile_set: TileSet,
+    #[serde(flatten)]
+    style: TileStyle,
+    #[serde(default)]
+    strict: bool,
+    /// Width of a tile, in millimetres.
+    #[serde(default = "default_tile_width")]
+    tile_width: f64,
+}
+
+#[tracing::instrument(
+    name = "render_request",
+    skip_all,
+    fields(%tile_set, hand = %hand, queue_wait_ms = Empty, render_ms = Empty, encode_ms = Empty)
+)]
+pub async fn render_hand_pdf(
+    UrlQuery(RenderHandPdfParams {
+        hand,
+        tile_set,
+        style,
+        strict,
+        tile_width,
+    }): UrlQuery<RenderHandPdfParams>,
+) -> Result<PdfResponse, ServiceError> {
+    let result: Result<_, ServiceError> = async {
+        validate_tile_width(tile_width)?;
+        let hand_obj = config().limits.render_limits().parse_hand(&hand)?;
+        let registered_tile_set = TILE_SETS.get(&tile_set)?;
+        let loaded_tile_set = registered_tile_set.load().await?;
+        let vector_tile_set = registered_tile_set.vector_tile_set;
+        let request = render_request(hand_obj, style, OutputFormat::Png, strict);
+        let warnings = request.check(&loaded_tile_set)?;
+
+        let permit = render_limiter().acquire().await?;
+        METRICS.record_queue_wait(permit.wait_time);
+        let span = Span::current();
+        span.record("queue_wait_ms", millis(permit.wait_time));
+        let queue_wait = permit.wait_time;
+        let tile_set_id = tile_set.clone();
+        let rendered = tokio::task::spawn_blocking(move || {
+            let _entered = span.enter();
+            let vector_tile_set = vector_tile_set.map(|load| load());
+            let rendered = request.render_pdf_checked(
+                &loaded_tile_set,
+                vector_tile_set,
+                tile_width,
+                warnings,
+            )?;
+
+            drop(permit);
+            span.record("render_ms", millis(rendered.render_time));
+            span.record("encode_ms", millis(rendered.encode_time));
+            info!("Rendered hand PDF");
+            METRICS.record_render(
+                &tile_set_id,
+                rendered.render_time,
+                rendered.encode_time,
+                rendered.data.len(),
+            );
+
+            Ok::<_, ServiceError>(rendered)
+        })
+        .await??;
+
+        Ok(PdfResponse::new(Bytes::from(rendered.data))
+            .with_file_name("hand.pdf")
+            .with_warnings(&rendered.warnings)
+            .with_timing("queue", queue_wait)
+            .with_timing("render", rendered.render_time)
+            .with_timing("encode", rendered.encode_time))
+    }
+    .await;
+    record_render_request(&tile_set, result)
+}
+
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+pub struct PrintSheetHand {
+    hand: String,
+    /// Text printed above the hand, e.g. a question number.
+    title: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+pub struct PrintSheetRequest {
+    hands: Vec<PrintSheetHand>,
+    tile_set: TileSet,
+    #[serde(flatten)]
+    style: TileStyle,
+    #[serde(default)]
+    strict: bool,
+    #[serde(default)]
+    paper: PaperSize,
+    #[serde(default = "default_columns")]
+    columns: usize,
+    /// Margin on every side of the pages, in millimetres.
+    #[serde(default = "default_margin")]
+    margin: f64,
+    /// Width of a tile, in millimetres; hands too wide for their column are
+    /// shrunk to fit it.
+    #[serde(default = "default_tile_width")]
+    tile_width: f64,
+    /// Text printed at the top of the first page.
+    title: Option<String>,
+}
+
+#[tracing::instrument(
+    name = "render_request",
+    skip_all,
+    fields(%tile_set, hands = hands.len(), queue_wait_ms = Empty, render_ms = Empty)
+)]
+pub async fn render_print_sheet(
+    Json(PrintSheetRequest {
+        hands,
+        tile_set,
+        style,
+        strict,
+        paper,
+        columns,
+        margin,
+        tile_width,
+        title,
+    }): Json<PrintSheetRequest>,
+) -> Result<PdfResponse, ServiceError> {
+    let result: Result<_, ServiceError> = async {
+        let max_hands = config().limits.max_print_sheet_hands.get();
+        if hands.is_empty() || hands.len() > max_hands {
+            return Err(ServiceError::BadRequest(format!(
+                "A print sheet must have between 1 and {max_hands} hands"
+            )));
+        }
+        if !(1..=MAX_COLUMNS).contains(&columns) {
+            return Err(ServiceError::BadRequest(format!(
+                "Number of columns must be between 1 and {MAX_COLUMNS}"
+            )));
+        }
+        if !(0.0..=MAX_MARGIN_MM).contains(&margin) {
+            return Err(ServiceError::BadRequest(format!(
+                "Margin must be between 0 and {MAX_MARGIN_MM} mm"
+            )));
+        }
+        validate_tile_width(tile_width)?;
+        validate_title(title.as_deref())?;
+
+        let registered_tile_set = TILE_SETS.get(&tile_set)?;
+        let loaded_tile_set = registered_tile_set.load().await?;
+        let vector_tile_set = registered_tile_set.vector_tile_set;
+        let limits = config().limits.render_limits();
+        let mut sheet_hands = Vec::with_capacity(hands.len());
+        let mut warnings = Vec::new();
+        for (index, hand) in hands.into_iter().enumerate() {
+            let number = index + 1;
+            validate_title(hand.title.as_deref())
+                .map_err(|error| ServiceError::BadRequest(format!("Hand {number}: {error}")))?;
+            let request = limits
+                .parse_hand(&hand.hand)
+                .map(|hand_obj| render_request(hand_obj, style, OutputFormat::Png, strict))
+                .map_err(|error| ServiceError::BadRequest(format!("Hand {number}: {error}")))?;
+            let hand_warnings = request
+                .check(&loaded_tile_set)
+                .map_err(|error| ServiceError::BadRequest(format!("Hand {number}: {error}")))?;
+            warnings.extend(hand_warnings.into_iter().map(|warning| HandWarning {
+                message: format!("Hand {number}: {}", warning.message),
+                ..warning
+            }));
+            sheet_hands.push(SheetHand {
+                request,
+                title: hand.title,
+            });
+        }
+        let sheet = PrintSheet {
+            paper,
+            columns,
+            margin_mm: margin,
+            tile_width_mm: tile_width,
+            title,
+            max_output_pixels: limits.max_output_pixels,
+        };
+        sheet.check_pixels(&sheet_hands, &loaded_tile_set)?;
+
+        let permit = render_limiter().acquire().await?;
+        METRICS.record_queue_wait(permit.wait_time);
+        let span = Span::current();
+        span.record("queue_wait_ms", millis(permit.wait_time));
+        let queue_wait = permit.wait_time;
+        let tile_set_id = tile_set.clone();
+        let (data, render_time) = tokio::task::spawn_blocking(move || {
+            let _entered = span.enter();
+            let render_time = Instant::now();
+            let vector_tile_set = vector_tile_set.map(|load| load());
+            let data = sheet.render_checked(&sheet_hands, &loaded_tile_set, vector_tile_set)?;
+            let render_time = render_time.elapsed();
+
+            drop(permit);
+            span.record("render_ms", millis(render_time));
+            info!("Rendered print sheet");
+            METRICS.record_render(&tile_set_id, render_time, Duration::ZERO, data.len());
+
+            Ok::<_, ServiceError>((data, render_time))
+        })
+        .await??;
+
+        Ok(PdfResponse::new(Bytes::from(data))
+            .with_file_name("print-sheet.pdf")
+            .with_warnings(&warnings)
+            .with_timing("queue", queue_wait)
+            .with_timing("render", render_time))
+    }
+    .await;
+    record_render_request(&tile_set, result)
+}
+
+fn validate_tile_width(tile_width: f64) -> Result<(), ServiceError> {
+    if (MIN_TILE_WIDTH_MM..=MAX_TILE_WIDTH_MM).contains(&tile_width) {
+        Ok(())
+    } else {
+        Err(ServiceError::BadRequest(format!(
+            "Tile width must be between {MIN_TILE_WIDTH_MM} and {MAX_TILE_WIDTH_MM} mm"
+        )))
+    }
+}
+
+fn validate_title(title: Option<&str>) -> Result<(), ServiceError> {
+    match title {
+        Some(title) if title.chars().count() > MAX_TITLE_LEN => Err(ServiceError::BadRequest(
+            format!("Title is too long (max {MAX_TITLE_LEN} characters)"),
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// PDF document with hands.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PdfDocument;
+
+impl ResponseContent for PdfDocument {
+    const MIME_TYPE: &'static str = "application/pdf";
+    const DESCRIPTION: &'static str = "PDF document";
+}
+
+type PdfResponse = ImageResponse<PdfDocument>;
@@ -0,0 +1,171 @@
+use std::error::Error as StdError;
+use std::fmt::{Display, Formatter};
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use cot::error::handler::RequestOuterError;
+use cot::http::{HeaderName, HeaderValue, Request};
+use cot::request::RequestHead;
+use cot::request::extractors::FromRequestHead;
+use cot::response::Response;
+use tower::{Layer, Service};
+use tracing::{Instrument, info_span};
+use uuid::Uuid;
+
+pub const REQUEST_ID_HEADER: HeaderName = HeaderName::from_static("x-request-id");
+const MAX_REQUEST_ID_LEN: usize = 128;
+
+/// Identifier of a request, used to correlate the logs of the reverse proxy,
+/// the backend, and the error messages shown to users.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct RequestId(String);
+
+impl RequestId {
+    fn generate() -> Self {
+        Self(Uuid::new_v4().to_string())
+    }
+
+    /// Returns the ID sent by the client or the reverse proxy, if it's short
+    /// and only contains printable ASCII characters.
+    fn from_header(value: &HeaderValue) -> Option<Self> {
+        let value = value.to_str().ok()?.trim();
+        let is_valid = !value.is_empty()
+            && value.len() <= MAX_REQUEST_ID_LEN
+            && value.chars().all(|c| c.is_ascii_graphic());
+        is_valid.then(|| Self(value.to_string()))
+    }
+
+    #[must_use]
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Display for RequestId {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl FromRequestHead for RequestId {
+    async fn from_request_head(head: &RequestHead) -> cot::Result<Self> {
+        head.extensions
+            .get::<Self>()
+            .cloned()
+            .ok_or_else(|| cot::Error::internal("request ID middleware is not installed"))
+    }
+}
+
+/// An error returned by a request handler, tagged with the ID of the request.
+///
+/// The error handler is called with a copy of the original request, without
+/// the extensions added by middlewares, so this is how the ID generated for a
+/// request reaches the error response.
+#[derive(Debug)]
+struct RequestIdError {
+    request_id: RequestId,
+    source: cot::Error,
+}
+
+impl Display for RequestIdError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        Display::fmt(&self.source, f)
+    }
+}
+
+impl StdError for RequestIdError {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        Some(&self.source)
+    }
+}
+
+/// Returns the ID of the request that failed with `error`, if it was tagged
+/// with one.
+fn failed_request_id(error: &cot::Error) -> Option<RequestId> {
+    let mut current: Option<&(dyn StdError + 'static)> = Some(&**error);
+    while let Some(error) = current {
+        if let Some(error) = error.downcast_ref::<RequestIdError>() {
+            return Some(error.request_id.clone());
+        }
+        current = match error.downcast_ref::<cot::Error>() {
+            Some(error) => Some(&**error),
+            None => error.source(),
+        };
+    }
+    None
+}
+
+/// Middleware assigning an ID to every request, taken from the `X-Request-Id`
+/// header or generated. The ID is available as a request extension, added to
+/// the tracing span of the request, and returned in the `X-Request-Id`
+/// response header.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RequestIdMiddleware;
+
+impl<S> Layer<S> for RequestIdMiddleware {
+    type Service = RequestIdService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        RequestIdService { inner }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RequestIdService<S> {
+    inner: S,
+}
+
+impl<ReqBody, S> Service<Request<ReqBody>> for RequestIdService<S>
+where
+    S: Service<Request<ReqBody>, Response = Response, Error = cot::Error>,
+    S::Future: Send + 'static,
+{
+    type Error = cot::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Response, cot::Error>> + Send>>;
+    type Response = Response;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut request: Request<ReqBody>) -> Self::Future {
+        let request_id = request
+            .extensions()
+            .get::<RequestOuterError>()
+            .and_then(|error| failed_request_id(error))
+            .or_else(|| {
+                request
+                    .headers()
+                    .get(REQUEST_ID_HEADER)
+                    .and_then(RequestId::from_header)
+            })
+            .unwrap_or_else(RequestId::generate);
+        request.extensions_mut().insert(request_id.clone());
+
+        let span = info_span!(
+            "request",
+            request_id = %request_id,
+            method = %request.method(),
+            path = %request.uri().path(),
+        );
+        let future = self.inner.call(request);
+        Box::pin(
+            async move {
+                match future.await {
+                    Ok(mut response) => {
+                        if let Ok(value) = HeaderValue::try_from(request_id.as_str()) {
+                            response.headers_mut().insert(REQUEST_ID_HEADER, value);
+                        }
+                        Ok(response)
+                    }
+                    Err(error) => Err(cot::Error::wrap(RequestIdError {
+                        request_id,
+                        source: error,
+                    })),
+                }
+            }
+            .instrument(span),
+        )
+    }
+}
@@ -0,0 +1,58 @@
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use async_trait::async_trait;
+use cot::cli::CliTask;
+use cot::cli::clap::{Arg, ArgMatches, Command, value_parser};
+use cot::project::{Bootstrapper, WithConfig};
+
+use crate::config::{DEFAULT_LISTEN, config};
+
+const LISTEN_PARAM: &str = "listen";
+
+static SERVE_COMMAND_RUNNING: AtomicBool = AtomicBool::new(false);
+
+/// Returns whether the server was started with the `serve` command, rather
+/// than without a command.
+pub fn serve_command_running() -> bool {
+    SERVE_COMMAND_RUNNING.load(Ordering::Relaxed)
+}
+
+/// `serve` subcommand running the server at the address from the config.
+///
+/// The server run without a subcommand always listens on the address given
+/// with `-l` (`127.0.0.1:8000` by default), as Cot doesn't allow changing it;
+/// a warning is logged if `listen` is set in the config then.
+pub struct ServeTask;
+
+#[async_trait(?Send)]
+impl CliTask for ServeTask {
+    fn subcommand(&self) -> Command {
+        Command::new("serve")
+            .about("Run the server at the address set in the config")
+            .arg(
+                Arg::new(LISTEN_PARAM)
+                    .help("Address to listen on instead of the one in the config")
+                    .short('l')
+                    .long(LISTEN_PARAM)
+                    .value_name("ADDRPORT")
+                    .value_parser(value_parser!(SocketAddr)),
+            )
+    }
+
+    async fn execute(
+        &mut self,
+        matches: &ArgMatches,
+        bootstrapper: Bootstrapper<WithConfig>,
+    ) -> cot::Result<()> {
+        let address = matches
+            .get_one::<SocketAddr>(LISTEN_PARAM)
+            .copied()
+            .or(config().listen)
+            .unwrap_or(DEFAULT_LISTEN);
+
+        SERVE_COMMAND_RUNNING.store(true, Ordering::Relaxed);
+        let bootstrapper = bootstrapper.boot().await?;
+        cot::run(bootstrapper, &address.to_string()).await
+    }
+}
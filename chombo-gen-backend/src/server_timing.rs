@@ -0,0 +1,44 @@
+use std::fmt::Write;
+use std::time::Duration;
+
+use cot::http::{HeaderName, HeaderValue};
+
+pub const SERVER_TIMING_HEADER: HeaderName = HeaderName::from_static("server-timing");
+
+/// Durations of the steps of handling a request, sent to the client in the
+/// `Server-Timing` header.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct ServerTiming {
+    metrics: Vec<(&'static str, Duration)>,
+}
+
+impl ServerTiming {
+    pub fn add(&mut self, name: &'static str, duration: Duration) {
+        self.metrics.push((name, duration));
+    }
+
+    /// Returns the value of the `Server-Timing` header, e.g.
+    /// `parse;dur=0.052, render;dur=12.3`, or `None` if nothing was measured.
+    #[must_use]
+    pub fn header_value(&self) -> Option<HeaderValue> {
+        if self.metrics.is_empty() {
+            return None;
+        }
+
+        let mut value = String::new();
+        for (index, (name, duration)) in self.metrics.iter().enumerate() {
+            if index > 0 {
+                value.push_str(", ");
+            }
+            write!(value, "{name};dur={:.3}", millis(*duration)).expect("writing to a String");
+        }
+        HeaderValue::try_from(value).ok()
+    }
+}
+
+/// Converts a duration to milliseconds, the unit used by `Server-Timing` and
+/// the timings recorded in tracing spans.
+#[must_use]
+pub fn millis(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
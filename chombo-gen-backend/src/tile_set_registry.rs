@@ -0,0 +1,302 @@
+use std::collections::VecDeque;
+use std::fmt::{Debug, Formatter};
+use std::path::PathBuf;
+use std::sync::{Arc, LazyLock, Mutex, RwLock};
+
+use chombo_gen_common::tile_set::{TileSet, TileSetInfo};
+use chombo_gen_render::tile_sets::{BUILT_IN_TILE_SETS, BuiltInTileSet};
+use chombo_gen_render::vector_tile_set::VectorTileSet;
+use indexmap::IndexMap;
+use riichi_hand::HandTile;
+use riichi_hand::raster_renderer::{TileImageResult, TileSet as RasterTileSet};
+use tracing::error;
+
+use crate::custom_tile_sets::{TileSetLoadError, read_tile_set};
+use crate::errors::ServiceError;
+
+/// Number of tile sets loaded on demand that are kept in memory at once.
+const MAX_RESIDENT_ON_DEMAND_TILE_SETS: usize = 8;
+
+pub static TILE_SETS: LazyLock<TileSetRegistry> = LazyLock::new(|| {
+    let registry = TileSetRegistry::new();
+    for tile_set in &BUILT_IN_TILE_SETS {
+        registry
+            .register(RegisteredTileSet::built_in(tile_set))
+            .expect("built-in tile set IDs are unique");
+    }
+    registry
+});
+
+/// Tile sets loaded on demand, the most recently used first.
+static RESIDENT_ON_DEMAND_TILE_SETS: LazyLock<Mutex<VecDeque<(PathBuf, SharedTileSet)>>> =
+    LazyLock::new(|| Mutex::new(VecDeque::new()));
+
+type SharedTileSet = Arc<dyn RasterTileSet + Send + Sync>;
+
+/// Where the tile images of a registered tile set come from.
+pub enum TileSetSource {
+    /// A tile set bundled with the binary, loaded lazily on first use.
+    Static(fn() -> &'static (dyn RasterTileSet + Sync)),
+    /// A tile set loaded at runtime, e.g. from a directory.
+    Loaded(SharedTileSet),
+    /// A tile set stored in a directory, loaded when it's used and dropped
+    /// from memory when enough other such tile sets are used after it.
+    OnDemand(OnDemandTileSet),
+}
+
+impl Debug for TileSetSource {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Static(_) => write!(f, "Static"),
+            Self::Loaded(_) => write!(f, "Loaded"),
+            Self::OnDemand(tile_set) => write!(f, "OnDemand({})", tile_set.dir.display()),
+        }
+    }
+}
+
+/// A tile set stored in a directory, in the format read by
+/// [`read_tile_set`].
+#[derive(Debug, Clone)]
+pub struct OnDemandTileSet {
+    pub dir: PathBuf,
+    pub tile_width: u32,
+    pub tile_height: u32,
+}
+
+impl OnDemandTileSet {
+    fn resident(&self) -> Option<SharedTileSet> {
+        let mut resident = RESIDENT_ON_DEMAND_TILE_SETS
+            .lock()
+            .expect("resident tile sets lock poisoned");
+        let index = resident.iter().position(|(dir, _)| *dir == self.dir)?;
+        let entry = resident.remove(index)?;
+        let tile_set = Arc::clone(&entry.1);
+        resident.push_front(entry);
+        Some(tile_set)
+    }
+
+    /// Returns the tile set, reading it from disk if it isn't in memory.
+    fn load(&self) -> Result<SharedTileSet, TileSetLoadError> {
+        if let Some(tile_set) = self.resident() {
+            return Ok(tile_set);
+        }
+
+        let tile_set: SharedTileSet = Arc::new(read_tile_set(&self.dir)?);
+        let mut resident = RESIDENT_ON_DEMAND_TILE_SETS
+            .lock()
+            .expect("resident tile sets lock poisoned");
+        resident.retain(|(dir, _)| *dir != self.dir);
+        resident.push_front((self.dir.clone(), Arc::clone(&tile_set)));
+        resident.truncate(MAX_RESIDENT_ON_DEMAND_TILE_SETS);
+        Ok(tile_set)
+    }
+
+    /// Drops the tile set from memory, e.g. before its directory is deleted.
+    pub fn evict(&self) {
+        RESIDENT_ON_DEMAND_TILE_SETS
+            .lock()
+            .expect("resident tile sets lock poisoned")
+            .retain(|(dir, _)| *dir != self.dir);
+    }
+}
+
+/// A tile set ready to render with, kept in memory for as long as this value
+/// exists.
+#[derive(Clone)]
+pub enum LoadedTileSet {
+    Static(&'static (dyn RasterTileSet + Sync)),
+    Shared(SharedTileSet),
+}
+
+impl LoadedTileSet {
+    fn tile_set(&self) -> &(dyn RasterTileSet + Sync) {
+        match self {
+            Self::Static(tile_set) => *tile_set,
+            Self::Shared(tile_set) => tile_set.as_ref(),
+        }
+    }
+}
+
+impl RasterTileSet for LoadedTileSet {
+    fn tile_image(&self, hand_tile: &HandTile) -> TileImageResult {
+        self.tile_set().tile_image(hand_tile)
+    }
+
+    fn tile_width(&self) -> u32 {
+        self.tile_set().tile_width()
+    }
+
+    fn tile_height(&self) -> u32 {
+        self.tile_set().tile_height()
+    }
+}
+
+impl Debug for LoadedTileSet {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Static(_) => write!(f, "Static"),
+            Self::Shared(_) => write!(f, "Shared"),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct RegisteredTileSet {
+    pub id: TileSet,
+    pub name: String,
+    pub attribution: String,
+    pub attribution_url: String,
+    pub license: String,
+    pub source: TileSetSource,
+    /// Loads the vector images of the tiles drawn in PDF documents, if the
+    /// tile set has them.
+    pub vector_tile_set: Option<fn() -> &'static VectorTileSet>,
+    /// Whether the tile set is included in the list of tile sets presented to
+    /// the users; unlisted tile sets can still be used by their ID.
+    pub listed: bool,
+}
+
+impl RegisteredTileSet {
+    fn built_in(tile_set: &BuiltInTileSet) -> Self {
+        Self {
+            id: TileSet::new(tile_set.id),
+            name: tile_set.name.to_string(),
+            attribution: tile_set.attribution.to_string(),
+            attribution_url: tile_set.attribution_url.to_string(),
+            license: tile_set.license.to_string(),
+            source: TileSetSource::Static(tile_set.loader()),
+            vector_tile_set: tile_set.vector_loader(),
+            listed: true,
+        }
+    }
+
+    /// Returns the tile set, reading it from disk in a blocking task if it's
+    /// loaded on demand and not in memory.
+    pub async fn load(&self) -> Result<LoadedTileSet, ServiceError> {
+        if let TileSetSource::OnDemand(tile_set) = &self.source
+            && tile_set.resident().is_none()
+        {
+            let tile_set = tile_set.clone();
+            let id = self.id.clone();
+            return tokio::task::spawn_blocking(move || {
+                tile_set
+                    .load()
+                    .map(LoadedTileSet::Shared)
+                    .map_err(|error| tile_set_load_failed(&id, &error))
+            })
+            .await?;
+        }
+
+        self.load_blocking()
+    }
+
+    /// Returns the tile set, reading it from disk on the current thread if
+    /// it's loaded on demand and not in memory.
+    pub fn load_blocking(&self) -> Result<LoadedTileSet, ServiceError> {
+        match &self.source {
+            TileSetSource::Static(tile_set) => Ok(LoadedTileSet::Static(tile_set())),
+            TileSetSource::Loaded(tile_set) => Ok(LoadedTileSet::Shared(Arc::clone(tile_set))),
+            TileSetSource::OnDemand(tile_set) => tile_set
+                .load()
+                .map(LoadedTileSet::Shared)
+                .map_err(|error| tile_set_load_failed(&self.id, &error)),
+        }
+    }
+
+    #[must_use]
+    pub fn info(&self) -> TileSetInfo {
+        let (tile_width, tile_height) = match &self.source {
+            TileSetSource::Static(tile_set) => (tile_set().tile_width(), tile_set().tile_height()),
+            TileSetSource::Loaded(tile_set) => (tile_set.tile_width(), tile_set.tile_height()),
+            TileSetSource::OnDemand(tile_set) => (tile_set.tile_width, tile_set.tile_height),
+        };
+
+        TileSetInfo {
+            id: self.id.clone(),
+            name: self.name.clone(),
+            attribution: self.attribution.clone(),
+            attribution_url: self.attribution_url.clone(),
+            license: self.license.clone(),
+            tile_width,
+            tile_height,
+        }
+    }
+}
+
+fn tile_set_load_failed(id: &TileSet, error: &TileSetLoadError) -> ServiceError {
+    error!("Could not load tile set {id}: {error}");
+    ServiceError::Internal(format!("Could not load tile set {id}"))
+}
+
+/// All tile sets that can be used for rendering, in the order they are
+/// presented to the users.
+#[derive(Debug)]
+pub struct TileSetRegistry {
+    tile_sets: RwLock<IndexMap<TileSet, Arc<RegisteredTileSet>>>,
+}
+
+impl TileSetRegistry {
+    #[must_use]
+    fn new() -> Self {
+        Self {
+            tile_sets: RwLock::new(IndexMap::new()),
+        }
+    }
+
+    pub fn register(&self, tile_set: RegisteredTileSet) -> Result<(), ServiceError> {
+        let mut tile_sets = self
+            .tile_sets
+            .write()
+            .expect("tile set registry lock poisoned");
+        if tile_sets.contains_key(&tile_set.id) {
+            return Err(ServiceError::BadRequest(format!(
+                "Tile set {} already exists",
+                tile_set.id
+            )));
+        }
+
+        tile_sets.insert(tile_set.id.clone(), Arc::new(tile_set));
+        Ok(())
+    }
+
+    pub fn get(&self, id: &TileSet) -> Result<Arc<RegisteredTileSet>, ServiceError> {
+        self.tile_sets
+            .read()
+            .expect("tile set registry lock poisoned")
+            .get(id)
+            .cloned()
+            .ok_or_else(|| ServiceError::BadRequest(format!("Unknown tile set: {id}")))
+    }
+
+    /// Removes all tile sets except the given ones; returns the first of them
+    /// that isn't registered, if any, without removing anything.
+    pub fn retain(&self, ids: &[TileSet]) -> Result<(), TileSet> {
+        let mut tile_sets = self
+            .tile_sets
+            .write()
+            .expect("tile set registry lock poisoned");
+        if let Some(id) = ids.iter().find(|id| !tile_sets.contains_key(*id)) {
+            return Err(id.clone());
+        }
+
+        tile_sets.retain(|id, _| ids.contains(id));
+        Ok(())
+    }
+
+    pub fn remove(&self, id: &TileSet) -> Option<Arc<RegisteredTileSet>> {
+        self.tile_sets
+            .write()
+            .expect("tile set registry lock poisoned")
+            .shift_remove(id)
+    }
+
+    #[must_use]
+    pub fn all(&self) -> Vec<Arc<RegisteredTileSet>> {
+        self.tile_sets
+            .read()
+            .expect("tile set registry lock poisoned")
+            .values()
+            .cloned()
+            .collect()
+    }
+}
@@ -0,0 +1,32 @@
+use chombo_gen_common::tile_set::{TileSet, TileSetInfo};
+use chombo_gen_render::tile_theme::TileStyle;
+use cot::json::Json;
+use cot::request::extractors::{Path, UrlQuery};
+use riichi_hand::parser::HandParser;
+
+use crate::errors::ServiceError;
+use crate::render_hand::{ImageResponse, record_render_request, render_hand};
+use crate::tile_set_registry::TILE_SETS;
+
+pub const PREVIEW_HAND: &str = "1230m_456p_789s_?77z?_EEE";
+
+pub async fn list_tile_sets() -> Json<Vec<TileSetInfo>> {
+    Json(
+        TILE_SETS
+            .all()
+            .iter()
+            .filter(|tile_set| tile_set.listed)
+            .map(|tile_set| tile_set.info())
+            .collect(),
+    )
+}
+
+pub async fn tile_set_preview(
+    Path(tile_set): Path<TileSet>,
+    UrlQuery(style): UrlQuery<TileStyle>,
+) -> Result<ImageResponse, ServiceError> {
+    let hand_obj = HandParser::parse(PREVIEW_HAND)?;
+
+    let result = render_hand(hand_obj, tile_set.clone(), style, false).await;
+    record_render_request(&tile_set, result)
+}
@@ -0,0 +1,451 @@
+use std::collections::{HashMap, HashSet};
+use std::io::{Cursor, Read};
+use std::path::{Path as FsPath, PathBuf};
+use std::sync::{LazyLock, Mutex, OnceLock};
+
+use chombo_gen_common::tile_set::{TileSet, TileSetInfo, UploadedTileSetResponse};
+use cot::http::header::CONTENT_LENGTH;
+use cot::json::Json;
+use cot::request::Request;
+use cot::request::extractors::{Path, UrlQuery};
+use riichi_hand::tiles::ALL_TILES;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use tracing::{error, info};
+use uuid::Uuid;
+use zip::ZipArchive;
+use zip::result::ZipError;
+
+use crate::api_keys::AuthenticatedApiKey;
+use crate::config::{LimitsConfig, config};
+use crate::custom_tile_sets::{
+    MANIFEST_FILE_NAME, TileSetLoadError, TileSetManifest, load_tile_set_with, parse_manifest,
+    read_manifest, tile_image_file_name, tile_set_dir_id, tile_set_dirs,
+};
+use crate::errors::ServiceError;
+use crate::render_cache::RENDER_CACHE;
+use crate::tile_set_registry::{OnDemandTileSet, RegisteredTileSet, TILE_SETS, TileSetSource};
+
+pub const UPLOADED_TILE_SETS_DIR_ENV: &str = "CHOMBO_GEN_UPLOADED_TILE_SETS_DIR";
+const UPLOADED_TILE_SET_ID_PREFIX: &str = "user-";
+const DELETE_TOKEN_FILE_NAME: &str = "delete_token";
+/// File containing the name of the API key a tile set was uploaded with.
+const OWNER_FILE_NAME: &str = "owner";
+
+const MAX_UPLOAD_SIZE: usize = 8 * 1024 * 1024;
+const MAX_UNCOMPRESSED_SIZE: u64 = 32 * 1024 * 1024;
+const MAX_ARCHIVE_ENTRIES: usize = 100;
+const MAX_TILE_WIDTH: u32 = 512;
+const MAX_TILE_HEIGHT: u32 = 512;
+const MAX_MANIFEST_FIELD_LEN: usize = 200;
+
+static UPLOADED_TILE_SETS_DIR: OnceLock<PathBuf> = OnceLock::new();
+static UPLOAD_USAGE: LazyLock<Mutex<UploadUsage>> =
+    LazyLock::new(|| Mutex::new(UploadUsage::default()));
+
+#[derive(Debug, Clone, Copy, Default)]
+struct StoredTileSets {
+    count: usize,
+    bytes: u64,
+}
+
+/// Number and size of the stored uploaded tile sets, in total and by the API
+/// key they were uploaded with.
+#[derive(Debug, Default)]
+struct UploadUsage {
+    total: StoredTileSets,
+    by_owner: HashMap<String, StoredTileSets>,
+}
+
+impl UploadUsage {
+    /// Counts a new tile set of `owner`, unless it would exceed the limits.
+    fn reserve(
+        &mut self,
+        owner: &str,
+        bytes: u64,
+        limits: &LimitsConfig,
+    ) -> Result<(), ServiceError> {
+        let owned = self.by_owner.get(owner).copied().unwrap_or_default();
+        if owned.count >= limits.max_uploaded_tile_sets_per_key.get()
+            || owned.bytes + bytes > limits.max_uploaded_bytes_per_key.get()
+        {
+            return Err(ServiceError::Forbidden(format!(
+                "This API key has too many uploaded tile sets (at most {} tile sets and {} bytes \
+                 are allowed); delete some of them first",
+                limits.max_uploaded_tile_sets_per_key, limits.max_uploaded_bytes_per_key
+            )));
+        }
+        if self.total.count >= limits.max_uploaded_tile_sets.get()
+            || self.total.bytes + bytes > limits.max_uploaded_bytes.get()
+        {
+            return Err(ServiceError::Forbidden(
+                "The server can't store any more uploaded tile sets".to_string(),
+            ));
+        }
+
+        self.add(owner, bytes);
+        Ok(())
+    }
+
+    fn add(&mut self, owner: &str, bytes: u64) {
+        let owned = self.by_owner.entry(owner.to_string()).or_default();
+        for usage in [owned, &mut self.total] {
+            usage.count += 1;
+            usage.bytes += bytes;
+        }
+    }
+
+    fn release(&mut self, owner: &str, bytes: u64) {
+        let owned = self.by_owner.entry(owner.to_string()).or_default();
+        for usage in [owned, &mut self.total] {
+            usage.count = usage.count.saturating_sub(1);
+            usage.bytes = usage.bytes.saturating_sub(bytes);
+        }
+    }
+}
+
+impl From<TileSetLoadError> for ServiceError {
+    fn from(error: TileSetLoadError) -> Self {
+        error!("{error:?}");
+        Self::BadRequest(error.to_string())
+    }
+}
+
+impl From<ZipError> for ServiceError {
+    fn from(error: ZipError) -> Self {
+        error!("{error:?}");
+        Self::BadRequest(format!("Invalid ZIP archive: {error}"))
+    }
+}
+
+/// Enables tile set uploads, storing them in `dir`, and registers the tile
+/// sets uploaded previously. Their images are only loaded when they're used.
+pub fn init_uploaded_tile_sets(dir: PathBuf) -> Result<Vec<RegisteredTileSet>, TileSetLoadError> {
+    std::fs::create_dir_all(&dir).map_err(|error| TileSetLoadError::Io(dir.clone(), error))?;
+    let mut tile_sets = Vec::new();
+    let mut usage = UPLOAD_USAGE.lock().expect("upload usage lock poisoned");
+    for tile_set_dir in tile_set_dirs(&dir)? {
+        let manifest = read_manifest(&tile_set_dir)?;
+        let (owner, bytes) = stored_tile_set_usage(&tile_set_dir)
+            .map_err(|error| TileSetLoadError::Io(tile_set_dir.clone(), error))?;
+        usage.add(&owner, bytes);
+        tile_sets.push(uploaded_tile_set(
+            tile_set_dir_id(&tile_set_dir),
+            &manifest,
+            tile_set_dir,
+        ));
+    }
+
+    UPLOADED_TILE_SETS_DIR
+        .set(dir)
+        .expect("uploaded tile sets directory initialized more than once");
+    Ok(tile_sets)
+}
+
+fn uploaded_tile_sets_dir() -> Result<&'static FsPath, ServiceError> {
+    UPLOADED_TILE_SETS_DIR
+        .get()
+        .map(PathBuf::as_path)
+        .ok_or_else(|| {
+            ServiceError::NotFound("Tile set uploads are disabled on this server".to_string())
+        })
+}
+
+/// Returns the directory of an uploaded tile set, or `None` if the ID does not
+/// belong to an uploaded tile set.
+fn uploaded_tile_set_dir(uploads_dir: &FsPath, id: &TileSet) -> Option<PathBuf> {
+    let uuid = id.id().strip_prefix(UPLOADED_TILE_SET_ID_PREFIX)?;
+    Uuid::try_parse(uuid).ok()?;
+    Some(uploads_dir.join(id.id()))
+}
+
+fn uploaded_tile_set(id: TileSet, manifest: &TileSetManifest, dir: PathBuf) -> RegisteredTileSet {
+    RegisteredTileSet {
+        id,
+        name: manifest.name.clone(),
+        attribution: manifest.attribution.clone(),
+        attribution_url: manifest.attribution_url.clone(),
+        license: manifest.license.clone(),
+        source: TileSetSource::OnDemand(OnDemandTileSet {
+            dir,
+            tile_width: manifest.tile_width,
+            tile_height: manifest.tile_height,
+        }),
+        vector_tile_set: None,
+        listed: false,
+    }
+}
+
+/// Returns the name of the API key a stored tile set was uploaded with
+/// (empty for tile sets uploaded before keys were required), and the size of
+/// its files.
+fn stored_tile_set_usage(tile_set_dir: &FsPath) -> std::io::Result<(String, u64)> {
+    let owner = match std::fs::read_to_string(tile_set_dir.join(OWNER_FILE_NAME)) {
+        Ok(owner) => owner.trim().to_string(),
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => String::new(),
+        Err(error) => return Err(error),
+    };
+    let mut bytes = 0;
+    for entry in std::fs::read_dir(tile_set_dir)? {
+        bytes += entry?.metadata()?.len();
+    }
+    Ok((owner, bytes))
+}
+
+pub async fn upload_tile_set(
+    request: Request,
+) -> Result<Json<UploadedTileSetResponse>, ServiceError> {
+    let uploads_dir = uploaded_tile_sets_dir()?;
+    let owner = request
+        .extensions()
+        .get::<AuthenticatedApiKey>()
+        .map(|api_key| api_key.key.name.clone())
+        .ok_or_else(|| {
+            ServiceError::Unauthorized(
+                "Uploading tile sets requires an API key in the X-API-Key header".to_string(),
+            )
+        })?;
+
+    let content_length = request
+        .headers()
+        .get(CONTENT_LENGTH)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse::<usize>().ok());
+    if content_length.is_some_and(|content_length| content_length > MAX_UPLOAD_SIZE) {
+        return Err(upload_too_large());
+    }
+    let archive = request
+        .into_body()
+        .into_bytes_limited(MAX_UPLOAD_SIZE)
+        .await
+        .map_err(|error| {
+            error!("{error:?}");
+            upload_too_large()
+        })?;
+
+    let id = TileSet::new(format!(
+        "{UPLOADED_TILE_SET_ID_PREFIX}{}",
+        Uuid::new_v4().simple()
+    ));
+    let delete_token = Uuid::new_v4().simple().to_string();
+
+    let tile_set = {
+        let delete_token = delete_token.clone();
+        let owner = owner.clone();
+        tokio::task::spawn_blocking(move || {
+            store_uploaded_tile_set(uploads_dir, id, &owner, &delete_token, &archive)
+        })
+        .await??
+    };
+    let tile_set_info = tile_set.info();
+    TILE_SETS.register(tile_set)?;
+    info!(
+        "Uploaded tile set {} ({}) with API key {owner}",
+        tile_set_info.id, tile_set_info.name
+    );
+
+    Ok(Json(UploadedTileSetResponse {
+        tile_set: tile_set_info,
+        delete_token,
+    }))
+}
+
+fn upload_too_large() -> ServiceError {
+    ServiceError::PayloadTooLarge(format!(
+        "Uploaded archive is too large (at most {MAX_UPLOAD_SIZE} bytes are allowed)"
+    ))
+}
+
+/// Validates the uploaded archive and saves its files in the tile set's
+/// directory, if `owner` has not used up their upload quota.
+fn store_uploaded_tile_set(
+    uploads_dir: &FsPath,
+    id: TileSet,
+    owner: &str,
+    delete_token: &str,
+    archive: &[u8],
+) -> Result<RegisteredTileSet, ServiceError> {
+    let files = extract_tile_set_files(archive)?;
+
+    let manifest_path = PathBuf::from(MANIFEST_FILE_NAME);
+    let manifest = files.get(&manifest_path).ok_or_else(|| {
+        ServiceError::BadRequest(format!("{MANIFEST_FILE_NAME} is missing from the archive"))
+    })?;
+    let manifest = parse_manifest(&manifest_path, manifest)?;
+    validate_manifest(&manifest)?;
+    // Decodes every tile image to check it; the decoded images are dropped,
+    // and read again from disk when the tile set is used
+    load_tile_set_with(id.clone(), FsPath::new(""), |path| {
+        Ok(files.get(path).cloned())
+    })?;
+
+    let bytes = files.values().map(|data| data.len() as u64).sum::<u64>()
+        + (owner.len() + delete_token.len()) as u64;
+    UPLOAD_USAGE
+        .lock()
+        .expect("upload usage lock poisoned")
+        .reserve(owner, bytes, &config().limits)?;
+
+    let tile_set_dir = uploads_dir.join(id.id());
+    let temp_dir = uploads_dir.join(format!(".{id}.tmp"));
+    let write_files = || {
+        std::fs::create_dir(&temp_dir)?;
+        for (path, data) in &files {
+            std::fs::write(temp_dir.join(path), data)?;
+        }
+        std::fs::write(temp_dir.join(DELETE_TOKEN_FILE_NAME), delete_token)?;
+        std::fs::write(temp_dir.join(OWNER_FILE_NAME), owner)?;
+        std::fs::rename(&temp_dir, &tile_set_dir)
+    };
+    if let Err(error) = write_files() {
+        error!("Could not save uploaded tile set {id}: {error:?}");
+        let _ = std::fs::remove_dir_all(&temp_dir);
+        UPLOAD_USAGE
+            .lock()
+            .expect("upload usage lock poisoned")
+            .release(owner, bytes);
+        return Err(ServiceError::Internal(
+            "Could not save the uploaded tile set".to_string(),
+        ));
+    }
+
+    Ok(uploaded_tile_set(id, &manifest, tile_set_dir))
+}
+
+/// Reads the files making up a tile set from a ZIP archive. The files may be
+/// placed in any directory inside the archive; other files are ignored.
+fn extract_tile_set_files(archive: &[u8]) -> Result<HashMap<PathBuf, Vec<u8>>, ServiceError> {
+    let mut archive = ZipArchive::new(Cursor::new(archive))?;
+    if archive.len() > MAX_ARCHIVE_ENTRIES {
+        return Err(ServiceError::BadRequest(format!(
+            "Archive contains too many files ({}/{MAX_ARCHIVE_ENTRIES})",
+            archive.len()
+        )));
+    }
+
+    let expected_file_names: HashSet<String> = ALL_TILES
+        .iter()
+        .map(tile_image_file_name)
+        .chain([MANIFEST_FILE_NAME.to_string()])
+        .collect();
+
+    let mut files = HashMap::new();
+    let mut remaining_size = MAX_UNCOMPRESSED_SIZE;
+    for index in 0..archive.len() {
+        let mut file = archive.by_index(index)?;
+        let file_name = file.enclosed_name().and_then(|path| {
+            path.file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+        });
+        let Some(file_name) = file_name else {
+            continue;
+        };
+        if !file.is_file() || !expected_file_names.contains(&file_name) {
+            continue;
+        }
+
+        // The sizes declared in the archive can't be trusted, so limit the
+        // amount of data actually decompressed.
+        let mut data = Vec::new();
+        (&mut file)
+            .take(remaining_size + 1)
+            .read_to_end(&mut data)
+            .map_err(|error| ServiceError::BadRequest(format!("Invalid ZIP archive: {error}")))?;
+        if data.len() as u64 > remaining_size {
+            return Err(ServiceError::PayloadTooLarge(format!(
+                "Uncompressed tile set is too large (at most {MAX_UNCOMPRESSED_SIZE} bytes are \
+                 allowed)"
+            )));
+        }
+        remaining_size -= data.len() as u64;
+
+        if files.insert(PathBuf::from(&file_name), data).is_some() {
+            return Err(ServiceError::BadRequest(format!(
+                "Archive contains more than one {file_name} file"
+            )));
+        }
+    }
+
+    Ok(files)
+}
+
+fn validate_manifest(manifest: &TileSetManifest) -> Result<(), ServiceError> {
+    if manifest.tile_width > MAX_TILE_WIDTH || manifest.tile_height > MAX_TILE_HEIGHT {
+        return Err(ServiceError::BadRequest(format!(
+            "Tiles are too large ({}x{}, at most {MAX_TILE_WIDTH}x{MAX_TILE_HEIGHT} pixels are \
+             allowed)",
+            manifest.tile_width, manifest.tile_height
+        )));
+    }
+
+    let fields = [
+        ("name", &manifest.name),
+        ("attribution", &manifest.attribution),
+        ("attribution_url", &manifest.attribution_url),
+        ("license", &manifest.license),
+    ];
+    for (field_name, value) in fields {
+        if value.len() > MAX_MANIFEST_FIELD_LEN {
+            return Err(ServiceError::BadRequest(format!(
+                "Manifest field {field_name} is too long ({}/{MAX_MANIFEST_FIELD_LEN} characters)",
+                value.len()
+            )));
+        }
+    }
+
+    Ok(())
+}
+
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+pub struct DeleteTileSetParams {
+    delete_token: String,
+}
+
+pub async fn delete_tile_set(
+    Path(tile_set): Path<TileSet>,
+    UrlQuery(DeleteTileSetParams { delete_token }): UrlQuery<DeleteTileSetParams>,
+) -> Result<Json<TileSetInfo>, ServiceError> {
+    let not_found = || ServiceError::NotFound(format!("Unknown uploaded tile set: {tile_set}"));
+
+    let uploads_dir = uploaded_tile_sets_dir()?;
+    let tile_set_dir = uploaded_tile_set_dir(uploads_dir, &tile_set).ok_or_else(not_found)?;
+    let expected_delete_token =
+        tokio::fs::read_to_string(tile_set_dir.join(DELETE_TOKEN_FILE_NAME))
+            .await
+            .map_err(|_| not_found())?;
+    if !secrets_match(&delete_token, expected_delete_token.trim()) {
+        return Err(ServiceError::Forbidden("Invalid delete token".to_string()));
+    }
+    let (owner, bytes) = {
+        let tile_set_dir = tile_set_dir.clone();
+        tokio::task::spawn_blocking(move || stored_tile_set_usage(&tile_set_dir))
+            .await?
+            .map_err(|_| not_found())?
+    };
+
+    let registered_tile_set = TILE_SETS.remove(&tile_set).ok_or_else(not_found)?;
+    if let TileSetSource::OnDemand(on_demand) = &registered_tile_set.source {
+        on_demand.evict();
+    }
+    RENDER_CACHE.remove_tile_set(&tile_set);
+    tokio::fs::remove_dir_all(&tile_set_dir)
+        .await
+        .map_err(|error| {
+            error!("Could not delete uploaded tile set {tile_set}: {error:?}");
+            ServiceError::Internal("Could not delete the uploaded tile set".to_string())
+        })?;
+    UPLOAD_USAGE
+        .lock()
+        .expect("upload usage lock poisoned")
+        .release(&owner, bytes);
+    info!("Deleted uploaded tile set {tile_set}");
+
+    Ok(Json(registered_tile_set.info()))
+}
+
+/// Compares secrets by their hashes, so that the time taken does not depend on
+/// how much of them matches.
+fn secrets_match(secret: &str, expected: &str) -> bool {
+    Sha256::digest(secret.as_bytes()) == Sha256::digest(expected.as_bytes())
+}
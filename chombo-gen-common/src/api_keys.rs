@@ -0,0 +1,18 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct ApiKeyUsageResponse {
+    pub name: String,
+    pub requests_per_minute: u32,
+    pub burst: u32,
+    /// Maximum number of requests per UTC day, or `None` if unlimited.
+    pub daily_quota: Option<u64>,
+    pub usage: ApiKeyUsageStats,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct ApiKeyUsageStats {
+    pub total_requests: u64,
+    pub requests_today: u64,
+}
@@ -0,0 +1,68 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct NormalizedHandResponse {
+    pub hand: String,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum HandWarningKind {
+    TooManyCopies,
+    TooManyRedFives,
+    WrongTileCount,
+    IncompleteKan,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct HandWarning {
+    pub kind: HandWarningKind,
+    pub message: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct HandLintResponse {
+    pub warnings: Vec<HandWarning>,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Suit {
+    Manzu,
+    Pinzu,
+    Souzu,
+    Honor,
+}
+
+/// Structured form of a hand, as produced by the hand parser.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct ParsedHand {
+    pub groups: Vec<ParsedGroup>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct ParsedGroup {
+    pub tiles: Vec<ParsedTile>,
+}
+
+/// A single tile of a hand.
+///
+/// Face-down tiles have neither `suit` nor `value`. Red fives have `value` set
+/// to 5 and `red` set to `true`. `shifted` tiles are rotated tiles stacked on
+/// top of the previous rotated tile (as in added kans).
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct ParsedTile {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub suit: Option<Suit>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub value: Option<u8>,
+    #[serde(default)]
+    pub red: bool,
+    #[serde(default)]
+    pub rotated: bool,
+    #[serde(default)]
+    pub shifted: bool,
+    #[serde(default)]
+    pub face_down: bool,
+}
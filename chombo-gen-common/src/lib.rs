@@ -1,3 +1,6 @@
+pub mod api_keys;
 pub mod enums;
 pub mod errors;
+pub mod hand;
+pub mod theme;
 pub mod tile_set;
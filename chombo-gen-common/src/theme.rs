@@ -0,0 +1,117 @@
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::enums::EnumName;
+
+/// Colours used for the tile faces, applied on top of any tile set.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize, JsonSchema, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum TileTheme {
+    /// Colours of the tile set.
+    #[default]
+    Default,
+    /// Saturated red, green, and blue that are easier to tell apart.
+    HighContrast,
+    /// Colours from the Okabe-Ito palette, distinguishable with most types of
+    /// colour vision deficiency.
+    Colorblind,
+}
+
+impl TileTheme {
+    /// All the themes, in the order they are presented to the users.
+    pub const ALL: [Self; 3] = [Self::Default, Self::HighContrast, Self::Colorblind];
+}
+
+impl EnumName for TileTheme {
+    fn name(&self) -> &'static str {
+        match self {
+            TileTheme::Default => "default",
+            TileTheme::HighContrast => "high_contrast",
+            TileTheme::Colorblind => "colorblind",
+        }
+    }
+}
+
+impl Display for TileTheme {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TileTheme::Default => {
+                write!(f, "Default")
+            }
+            TileTheme::HighContrast => {
+                write!(f, "High contrast")
+            }
+            TileTheme::Colorblind => {
+                write!(f, "Colourblind-friendly")
+            }
+        }
+    }
+}
+
+/// An RGB colour, written as `#rrggbb`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize, JsonSchema)]
+#[serde(try_from = "String", into = "String")]
+#[schemars(with = "String")]
+pub struct Color {
+    pub red: u8,
+    pub green: u8,
+    pub blue: u8,
+}
+
+impl Color {
+    #[must_use]
+    pub const fn new(red: u8, green: u8, blue: u8) -> Self {
+        Self { red, green, blue }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseColorError(String);
+
+impl Display for ParseColorError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "invalid colour `{}`, expected #rrggbb", self.0)
+    }
+}
+
+impl Error for ParseColorError {}
+
+impl FromStr for Color {
+    type Err = ParseColorError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let hex = s.strip_prefix('#').unwrap_or(s);
+        if hex.len() != 6 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(ParseColorError(s.to_string()));
+        }
+
+        let component = |index: usize| {
+            u8::from_str_radix(&hex[index..index + 2], 16).expect("checked to be hex digits")
+        };
+        Ok(Self::new(component(0), component(2), component(4)))
+    }
+}
+
+impl TryFrom<String> for Color {
+    type Error = ParseColorError;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<Color> for String {
+    fn from(color: Color) -> Self {
+        color.to_string()
+    }
+}
+
+impl Display for Color {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "#{:02x}{:02x}{:02x}", self.red, self.green, self.blue)
+    }
+}
@@ -1,45 +1,53 @@
 use std::fmt::{Display, Formatter};
 
 use schemars::JsonSchema;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
-use crate::enums::EnumName;
+const DEFAULT_TILE_SET: &str = "Yellow";
 
-#[derive(Copy, Clone, Debug, PartialEq, Eq, Deserialize, JsonSchema, Default)]
-pub enum TileSet {
-    #[default]
-    Yellow,
-    Red,
-    Black,
-    MartinPersson,
+/// Identifier of a tile set registered in the backend.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize, JsonSchema)]
+#[serde(transparent)]
+pub struct TileSet(String);
+
+impl TileSet {
+    #[must_use]
+    pub fn new<T: Into<String>>(id: T) -> Self {
+        Self(id.into())
+    }
+
+    #[must_use]
+    pub fn id(&self) -> &str {
+        &self.0
+    }
 }
 
-impl EnumName for TileSet {
-    fn name(&self) -> &'static str {
-        match self {
-            TileSet::Yellow => "Yellow",
-            TileSet::Red => "Red",
-            TileSet::Black => "Black",
-            TileSet::MartinPersson => "MartinPersson",
-        }
+impl Default for TileSet {
+    fn default() -> Self {
+        Self::new(DEFAULT_TILE_SET)
     }
 }
 
 impl Display for TileSet {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        match self {
-            TileSet::Yellow => {
-                write!(f, "Yellow")
-            }
-            TileSet::Red => {
-                write!(f, "Red")
-            }
-            TileSet::Black => {
-                write!(f, "Black")
-            }
-            TileSet::MartinPersson => {
-                write!(f, "Martin Persson")
-            }
-        }
+        write!(f, "{}", self.0)
     }
 }
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct TileSetInfo {
+    pub id: TileSet,
+    pub name: String,
+    pub attribution: String,
+    pub attribution_url: String,
+    pub license: String,
+    pub tile_width: u32,
+    pub tile_height: u32,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct UploadedTileSetResponse {
+    pub tile_set: TileSetInfo,
+    /// Secret needed to delete the uploaded tile set.
+    pub delete_token: String,
+}
@@ -0,0 +1,5 @@
+const API_URL: Option<&str> = option_env!("CHOMBO_GEN_API_URL");
+
+pub fn api_url() -> &'static str {
+    API_URL.unwrap_or("/api")
+}
@@ -1,9 +1,42 @@
+use chombo_gen_common::tile_set::TileSetInfo;
+use gloo_net::http::Request;
 use yew::prelude::*;
 
+use crate::api::api_url;
 use crate::hand_generator::HandGenerator;
 
 #[function_component(App)]
 pub fn app() -> Html {
+    let tile_sets = use_state(Vec::<TileSetInfo>::new);
+    {
+        let tile_sets = tile_sets.clone();
+
+        use_effect_with((), move |_| {
+            wasm_bindgen_futures::spawn_local(async move {
+                let result = Request::get(&format!("{}/tile-sets", api_url()))
+                    .send()
+                    .await
+                    .unwrap();
+
+                if result.ok() {
+                    let infos: Vec<TileSetInfo> = result.json().await.unwrap();
+                    tile_sets.set(infos);
+                }
+            });
+            || ()
+        });
+    }
+
+    let mut attributions: Vec<(&str, &str)> = Vec::new();
+    for info in tile_sets.iter() {
+        if !attributions
+            .iter()
+            .any(|(_, url)| *url == info.attribution_url)
+        {
+            attributions.push((&info.attribution, &info.attribution_url));
+        }
+    }
+
     html! {
         <>
             <nav class="navbar navbar-expand-md navbar-dark bg-dark mb-4">
@@ -27,12 +60,27 @@ pub fn app() -> Html {
 
             <main class="container">
                 <div class="bg-body-tertiary p-5 rounded">
-                    <HandGenerator />
+                    <HandGenerator tile_sets={ (*tile_sets).clone() } />
                 </div>
 
-                <div class="text-center mt-4 text-secondary"><small>
-                    { "This uses "}<a href="https://github.com/FluffyStuff/riichi-mahjong-tiles">{ "riichi-mahjong-tiles by FluffyStuff" }</a>{ ", and mahjong tiles by " }<a href="https://www.martinpersson.org/">{ "Martin Persson" }</a>{ "." }
-                </small></div>
+                if !attributions.is_empty() {
+                    <div class="text-center mt-4 text-secondary"><small>
+                        { "This uses " }
+                        {
+                            attributions.iter().enumerate().map(|(index, (attribution, url))| {
+                                let separator = match index {
+                                    0 => "",
+                                    _ if index == attributions.len() - 1 => ", and ",
+                                    _ => ", ",
+                                };
+                                html! {
+                                    <>{ separator }<a href={ url.to_string() }>{ attribution.to_string() }</a></>
+                                }
+                            }).collect::<Html>()
+                        }
+                        { "." }
+                    </small></div>
+                }
             </main>
         </>
     }
@@ -1,19 +1,25 @@
 use base64::Engine;
 use chombo_gen_common::enums::EnumName;
+use chombo_gen_common::theme::TileTheme;
 use chombo_gen_common::tile_set::TileSet;
 use gloo_net::http::Request;
 use serde::{Deserialize, Serialize};
 use yew::prelude::*;
 
+use crate::api::api_url;
+
 #[derive(Clone, PartialEq, Properties)]
 pub struct Props {
     pub hand: AttrValue,
     pub tile_set: TileSet,
+    pub theme: TileTheme,
 }
 
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct ServiceErrorResponse {
     message: String,
+    #[serde(default)]
+    request_id: Option<String>,
 }
 
 #[derive(Clone, Debug, Default)]
@@ -25,23 +31,22 @@ pub enum HandState {
     Empty,
 }
 
-const API_URL: Option<&str> = option_env!("CHOMBO_GEN_API_URL");
-
-fn api_url() -> &'static str {
-    API_URL.unwrap_or("/api")
-}
-
 #[function_component]
 pub fn Hand(props: &Props) -> Html {
-    let Props { hand, tile_set } = props;
+    let Props {
+        hand,
+        tile_set,
+        theme,
+    } = props;
 
     let hand_state = use_state(HandState::default);
     {
         let hand_state = hand_state.clone();
         let hand_val = hand.clone();
-        let tile_set = *tile_set;
+        let tile_set = tile_set.clone();
+        let theme = *theme;
 
-        use_effect_with((hand.clone(), tile_set), move |_| {
+        use_effect_with((hand.clone(), tile_set.clone(), theme), move |_| {
             if !hand_val.is_empty() {
                 hand_state.set(HandState::Loading);
                 let hand_state = hand_state.clone();
@@ -49,7 +54,8 @@ pub fn Hand(props: &Props) -> Html {
                 wasm_bindgen_futures::spawn_local(async move {
                     let result = Request::get(&format!("{}/hand/", api_url()))
                         .query([("hand", hand_val)])
-                        .query([("tile_set", tile_set.name())])
+                        .query([("tile_set", tile_set.id())])
+                        .query([("theme", theme.name())])
                         .send()
                         .await
                         .unwrap();
@@ -88,6 +94,11 @@ pub fn Hand(props: &Props) -> Html {
         HandState::Error(error) => html! {
             <div class="alert alert-danger" role="alert">
                 <strong>{ "Error: " }</strong>{ &error.message }
+                if let Some(request_id) = &error.request_id {
+                    <div class="small text-body-secondary mt-1">
+                        { "Request ID: " }<code>{ request_id }</code>
+                    </div>
+                }
             </div>
         },
         HandState::Loading => html! {
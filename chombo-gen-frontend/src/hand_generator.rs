@@ -1,4 +1,6 @@
-use chombo_gen_common::tile_set::TileSet;
+use chombo_gen_common::enums::EnumName;
+use chombo_gen_common::theme::TileTheme;
+use chombo_gen_common::tile_set::{TileSet, TileSetInfo};
 use yew::prelude::*;
 
 use crate::hand::Hand;
@@ -6,17 +8,31 @@ use crate::hand_example::HandExample;
 use crate::input::Input;
 use crate::select::Select;
 
+#[derive(Clone, PartialEq, Properties)]
+pub struct Props {
+    pub tile_sets: Vec<TileSetInfo>,
+}
+
 #[function_component]
-pub fn HandGenerator() -> Html {
+pub fn HandGenerator(props: &Props) -> Html {
     let hand = use_state(String::default);
     let hand_rendered = use_state(String::default);
     let tile_set = use_state(TileSet::default);
+    let theme = use_state(TileTheme::default);
 
-    let on_tile_set_select: Callback<TileSet> = {
+    let on_tile_set_select: Callback<TileSetInfo> = {
         let tile_set = tile_set.clone();
 
-        Callback::from(move |value: TileSet| {
-            tile_set.set(value);
+        Callback::from(move |value: TileSetInfo| {
+            tile_set.set(value.id);
+        })
+    };
+
+    let on_theme_select: Callback<TileTheme> = {
+        let theme = theme.clone();
+
+        Callback::from(move |value: TileTheme| {
+            theme.set(value);
         })
     };
 
@@ -38,12 +54,8 @@ pub fn HandGenerator() -> Html {
         })
     };
 
-    let options = vec![
-        TileSet::Yellow,
-        TileSet::Red,
-        TileSet::Black,
-        TileSet::MartinPersson,
-    ];
+    let options = props.tile_sets.clone();
+    let theme_options = TileTheme::ALL.to_vec();
 
     html! {
         <>
@@ -51,7 +63,11 @@ pub fn HandGenerator() -> Html {
 
             <div class="mb-3">
                 <label for="tile-set-select" class="form-label">{ "Tile Set" }</label>
-                <Select<TileSet> on_set_value={ on_tile_set_select } { options } id="tile-set-select" />
+                <Select<TileSetInfo> on_set_value={ on_tile_set_select } { options } selected={ tile_set.id().to_string() } id="tile-set-select" />
+            </div>
+            <div class="mb-3">
+                <label for="theme-select" class="form-label">{ "Colours" }</label>
+                <Select<TileTheme> on_set_value={ on_theme_select } options={ theme_options } selected={ theme.name().to_string() } id="theme-select" />
             </div>
             <div class="mb-3">
                 <label for="hand" class="form-label">{ "Hand" }</label>
@@ -95,7 +111,7 @@ pub fn HandGenerator() -> Html {
                 </div>
             </div>
 
-            <Hand hand={ (*hand).clone() } tile_set={ *tile_set } />
+            <Hand hand={ (*hand).clone() } tile_set={ (*tile_set).clone() } theme={ *theme } />
         </>
     }
 }
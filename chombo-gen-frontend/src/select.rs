@@ -1,22 +1,53 @@
-use std::fmt::Display;
-
 use chombo_gen_common::enums::EnumName;
+use chombo_gen_common::theme::TileTheme;
+use chombo_gen_common::tile_set::TileSetInfo;
 use wasm_bindgen::JsCast;
 use web_sys::{EventTarget, HtmlSelectElement};
 use yew::prelude::*;
 
+/// A value that can be chosen in a [`Select`].
+pub trait SelectOption {
+    /// Identifies the option; compared with [`Props::selected`].
+    fn value(&self) -> &str;
+
+    /// Text shown to the user.
+    fn label(&self) -> String;
+}
+
+impl SelectOption for TileTheme {
+    fn value(&self) -> &str {
+        self.name()
+    }
+
+    fn label(&self) -> String {
+        self.to_string()
+    }
+}
+
+impl SelectOption for TileSetInfo {
+    fn value(&self) -> &str {
+        self.id.id()
+    }
+
+    fn label(&self) -> String {
+        self.name.clone()
+    }
+}
+
 #[derive(Clone, PartialEq, Properties)]
 pub struct Props<T: PartialEq> {
     #[prop_or_default]
     pub id: AttrValue,
     pub options: Vec<T>,
+    #[prop_or_default]
+    pub selected: AttrValue,
     pub on_set_value: Callback<T>,
 }
 
 #[function_component]
 pub fn Select<T>(props: &Props<T>) -> Html
 where
-    T: PartialEq + Copy + Default + EnumName + Display + 'static,
+    T: PartialEq + Clone + SelectOption + 'static,
 {
     let on_change = {
         let props = props.clone();
@@ -27,7 +58,7 @@ where
 
             if let Some(input) = input {
                 let selected_index = input.selected_index() as usize;
-                let selected_value = props.options[selected_index];
+                let selected_value = props.options[selected_index].clone();
                 props.on_set_value.emit(selected_value);
             }
         })
@@ -37,7 +68,7 @@ where
         <select onchange={on_change} class="form-select" aria-label="Tile Set" id={props.id.clone()}>
             {
                 props.options.iter().map(|option| {
-                    html!{<option value={ option.name() } selected={ *option == T::default() }>{ option.to_string() }</option>}
+                    html!{<option value={ option.value().to_string() } selected={ option.value() == props.selected.as_str() }>{ option.label() }</option>}
                 }).collect::<Html>()
             }
         </select>
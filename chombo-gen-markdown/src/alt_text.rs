@@ -0,0 +1,58 @@
+use riichi_hand::{Hand, HandTile, Suite, TilePlacement};
+
+const HONOR_NAMES: [&str; 7] = [
+    "East wind",
+    "South wind",
+    "West wind",
+    "North wind",
+    "White dragon",
+    "Green dragon",
+    "Red dragon",
+];
+
+/// Describes a hand in words, for the alternative text of its image, e.g.
+/// `Mahjong hand: 1 man, 2 man, 3 man; East wind (sideways), East wind, East
+/// wind`.
+#[must_use]
+pub fn alt_text(hand: &Hand) -> String {
+    let groups = hand
+        .groups()
+        .iter()
+        .filter(|group| !group.is_empty())
+        .map(|group| {
+            group
+                .iter()
+                .map(tile_description)
+                .collect::<Vec<_>>()
+                .join(", ")
+        })
+        .collect::<Vec<_>>()
+        .join("; ");
+
+    format!("Mahjong hand: {groups}")
+}
+
+fn tile_description(hand_tile: &HandTile) -> String {
+    let value = hand_tile.tile.value.0;
+    let name = match hand_tile.tile.suite {
+        Suite::Manzu => number_tile(value, "man"),
+        Suite::Pinzu => number_tile(value, "pin"),
+        Suite::Souzu => number_tile(value, "sou"),
+        Suite::Honor => HONOR_NAMES[usize::from(value) - 1].to_string(),
+        Suite::Any => "face-down tile".to_string(),
+    };
+
+    match hand_tile.placement {
+        TilePlacement::Normal => name,
+        TilePlacement::Rotated => format!("{name} (sideways)"),
+        TilePlacement::RotatedAndShifted => format!("{name} (sideways, added to the kan)"),
+    }
+}
+
+fn number_tile(value: u8, suite: &str) -> String {
+    if value == 0 {
+        format!("red 5 {suite}")
+    } else {
+        format!("{value} {suite}")
+    }
+}
@@ -0,0 +1,183 @@
+//! mdBook preprocessor embedding images of the hands in the chapters.
+//!
+//! mdBook runs it with the book as JSON on the standard input, and reads the
+//! modified book from the standard output.
+
+use std::io::Read;
+use std::process::ExitCode;
+
+use chombo_gen_markdown::{ImageOutput, MarkdownError, MarkdownOptions, process_markdown};
+use chombo_gen_render::OutputFormat;
+use chombo_gen_render::lint::warnings_summary;
+use chombo_gen_render::tile_sets::{BUILT_IN_TILE_SETS, built_in_tile_set};
+use chombo_gen_render::tile_theme::TileStyle;
+use clap::{Arg, Command};
+use serde::Deserialize;
+use serde_json::Value;
+
+/// Name of the preprocessor, i.e. of its table in `book.toml`.
+const PREPROCESSOR_NAME: &str = "chombo-gen";
+const SUPPORTS_COMMAND: &str = "supports";
+const RENDERER_PARAM: &str = "renderer";
+
+/// Settings in the `[preprocessor.chombo-gen]` table of `book.toml`.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+struct PreprocessorConfig {
+    /// Built-in tile set to render the hands with; `Yellow` if not set.
+    tile_set: Option<String>,
+    format: Option<String>,
+    theme: Option<String>,
+    back_color: Option<String>,
+    scale: Option<u32>,
+    strict: bool,
+}
+
+fn command() -> Command {
+    Command::new("mdbook-chombo-gen")
+        .version(env!("CARGO_PKG_VERSION"))
+        .about("mdBook preprocessor replacing mahjong hands with images")
+        .subcommand(
+            Command::new(SUPPORTS_COMMAND)
+                .about("Check whether a renderer is supported")
+                .arg(Arg::new(RENDERER_PARAM).required(true)),
+        )
+}
+
+fn main() -> ExitCode {
+    let matches = command().get_matches();
+    // The images are embedded as Markdown image links, which all renderers
+    // understand.
+    if matches.subcommand_matches(SUPPORTS_COMMAND).is_some() {
+        return ExitCode::SUCCESS;
+    }
+
+    match run() {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            eprintln!("error: {error}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run() -> Result<(), String> {
+    let mut input = String::new();
+    std::io::stdin()
+        .read_to_string(&mut input)
+        .map_err(|error| format!("could not read the book: {error}"))?;
+    let (context, mut book): (Value, Value) =
+        serde_json::from_str(&input).map_err(|error| format!("invalid book: {error}"))?;
+
+    let config: PreprocessorConfig = context
+        .pointer(&format!("/config/preprocessor/{PREPROCESSOR_NAME}"))
+        .cloned()
+        .map(serde_json::from_value)
+        .transpose()
+        .map_err(|error| format!("invalid [preprocessor.{PREPROCESSOR_NAME}] settings: {error}"))?
+        .unwrap_or_default();
+    let tile_set_id = config.tile_set.as_deref().unwrap_or("Yellow");
+    let tile_set = built_in_tile_set(tile_set_id).ok_or_else(|| {
+        let available: Vec<_> = BUILT_IN_TILE_SETS
+            .iter()
+            .map(|tile_set| tile_set.id)
+            .collect();
+        format!(
+            "unknown tile set `{tile_set_id}`, expected one of: {}",
+            available.join(", ")
+        )
+    })?;
+    let options = MarkdownOptions {
+        style: tile_style(&config)?,
+        format: output_format(config.format.as_deref())?,
+        strict: config.strict,
+        ..MarkdownOptions::new(tile_set.tile_set(), ImageOutput::Embedded)
+    };
+
+    let mut errors = Vec::new();
+    for_each_chapter(&mut book, &mut |chapter| {
+        let Some(content) = chapter.get("content").and_then(Value::as_str) else {
+            return;
+        };
+        let path = ["source_path", "path", "name"]
+            .into_iter()
+            .find_map(|key| chapter.get(key).and_then(Value::as_str))
+            .unwrap_or_default()
+            .to_string();
+
+        match process_markdown(content, &options) {
+            Ok(processed) => {
+                for warning in &processed.warnings {
+                    eprintln!(
+                        "warning: {path}:{}: {}: {}",
+                        warning.line,
+                        warning.hand,
+                        warnings_summary(&warning.warnings)
+                    );
+                }
+                chapter.insert("content".to_string(), Value::String(processed.markdown));
+            }
+            Err(MarkdownError::Render { line, hand, error }) => {
+                errors.push(format!(
+                    "{path}:{line}: could not render hand `{hand}`: {error}"
+                ));
+            }
+            Err(error) => errors.push(format!("{path}: {error}")),
+        }
+    });
+    if !errors.is_empty() {
+        return Err(errors.join("\nerror: "));
+    }
+
+    serde_json::to_writer(std::io::stdout(), &book)
+        .map_err(|error| format!("could not write the book: {error}"))
+}
+
+/// Calls `process` with every chapter of the book, including nested ones.
+fn for_each_chapter(
+    value: &mut Value,
+    process: &mut impl FnMut(&mut serde_json::Map<String, Value>),
+) {
+    match value {
+        Value::Object(object) => {
+            if let Some(Value::Object(chapter)) = object.get_mut("Chapter") {
+                process(chapter);
+            }
+            for value in object.values_mut() {
+                for_each_chapter(value, process);
+            }
+        }
+        Value::Array(values) => {
+            for value in values {
+                for_each_chapter(value, process);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn tile_style(config: &PreprocessorConfig) -> Result<TileStyle, String> {
+    let scale = config.scale.map(|scale| scale.to_string());
+    TileStyle::from_options(
+        config.theme.as_deref(),
+        config.back_color.as_deref(),
+        scale.as_deref(),
+    )
+    .map_err(|error| format!("invalid tile style: {error}"))
+}
+
+fn output_format(format: Option<&str>) -> Result<OutputFormat, String> {
+    let Some(format) = format else {
+        return Ok(OutputFormat::default());
+    };
+    OutputFormat::from_extension(format).ok_or_else(|| {
+        let available: Vec<_> = OutputFormat::ALL
+            .iter()
+            .map(|format| format!("`{}`", format.extension()))
+            .collect();
+        format!(
+            "unknown image format `{format}`, expected one of: {}",
+            available.join(", ")
+        )
+    })
+}
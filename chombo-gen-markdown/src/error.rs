@@ -0,0 +1,38 @@
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::path::PathBuf;
+
+use chombo_gen_render::RenderError;
+
+#[derive(Debug)]
+pub enum MarkdownError {
+    Render {
+        /// Number of the line the hand is on, starting at 1.
+        line: usize,
+        hand: String,
+        error: RenderError,
+    },
+    Write(PathBuf, std::io::Error),
+}
+
+impl Display for MarkdownError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Render { line, hand, error } => {
+                write!(f, "line {line}: could not render hand `{hand}`: {error}")
+            }
+            Self::Write(path, error) => {
+                write!(f, "could not write {}: {error}", path.display())
+            }
+        }
+    }
+}
+
+impl Error for MarkdownError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Render { error, .. } => Some(error),
+            Self::Write(_, error) => Some(error),
+        }
+    }
+}
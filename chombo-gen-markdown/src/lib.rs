@@ -0,0 +1,230 @@
+//! Replaces the mahjong hands in Markdown documents with images rendered by
+//! ChomboGen.
+//!
+//! Hands are written in fenced code blocks with the `hand` info string, one
+//! hand per line, or inline as `{hand:123m456p789s11z}` shortcodes.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use base64::Engine;
+use chombo_gen_common::hand::HandWarning;
+use chombo_gen_render::tile_theme::TileStyle;
+use chombo_gen_render::{OutputFormat, RenderLimits, RenderRequest};
+use riichi_hand::raster_renderer::TileSet as RasterTileSet;
+use sha2::{Digest, Sha256};
+
+mod alt_text;
+mod error;
+mod scan;
+
+pub use alt_text::alt_text;
+pub use error::MarkdownError;
+pub use scan::{HAND_LANGUAGE, HandMatch, HandSource, replace_hands};
+
+/// Number of hex digits of the image hash used in the asset file names.
+const ASSET_HASH_LEN: usize = 16;
+
+/// Where the images of the hands go.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImageOutput<'a> {
+    /// Image files in an assets directory.
+    Assets {
+        /// Directory the images are written to.
+        dir: &'a Path,
+        /// Path or URL of the assets directory used in the image links,
+        /// e.g. relative to the output document.
+        url: &'a str,
+    },
+    /// `data:` URLs in the document itself.
+    Embedded,
+}
+
+/// How the hands in a document are rendered, and where the images go.
+#[derive(Clone, Copy)]
+pub struct MarkdownOptions<'a> {
+    pub tile_set: &'a dyn RasterTileSet,
+    pub style: TileStyle,
+    pub format: OutputFormat,
+    /// Whether to reject hands that have lint warnings.
+    pub strict: bool,
+    pub limits: RenderLimits,
+    pub images: ImageOutput<'a>,
+}
+
+impl<'a> MarkdownOptions<'a> {
+    /// Creates options rendering the hands in the default style.
+    #[must_use]
+    pub fn new(tile_set: &'a dyn RasterTileSet, images: ImageOutput<'a>) -> Self {
+        Self {
+            tile_set,
+            style: TileStyle::default(),
+            format: OutputFormat::default(),
+            strict: false,
+            limits: RenderLimits::default(),
+            images,
+        }
+    }
+}
+
+/// Warnings about a hand in a document.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DocumentWarning {
+    /// Number of the line the hand is on, starting at 1.
+    pub line: usize,
+    pub hand: String,
+    pub warnings: Vec<HandWarning>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProcessedMarkdown {
+    /// The document with the hands replaced by image links.
+    pub markdown: String,
+    /// Images linked from the document, in the assets directory; empty if
+    /// the images are embedded.
+    pub images: Vec<PathBuf>,
+    pub warnings: Vec<DocumentWarning>,
+}
+
+/// Renders the hands in a Markdown document, and replaces them with image
+/// links.
+///
+/// Images written to an assets directory are named after the hash of their
+/// content, so unchanged hands keep their file names, and existing files are
+/// not rewritten.
+pub fn process_markdown(
+    markdown: &str,
+    options: &MarkdownOptions<'_>,
+) -> Result<ProcessedMarkdown, MarkdownError> {
+    // Links and warnings of the hands already rendered, by hand
+    let mut links: HashMap<String, (String, Vec<HandWarning>)> = HashMap::new();
+    let mut images = Vec::new();
+    let mut warnings = Vec::new();
+
+    let markdown = replace_hands(markdown, |hand_match| {
+        if let Some((link, hand_warnings)) = links.get(hand_match.hand) {
+            if !hand_warnings.is_empty() {
+                warnings.push(DocumentWarning {
+                    line: hand_match.line,
+                    hand: hand_match.hand.to_string(),
+                    warnings: hand_warnings.clone(),
+                });
+            }
+            return Ok(link.clone());
+        }
+
+        let render_error = |error| MarkdownError::Render {
+            line: hand_match.line,
+            hand: hand_match.hand.to_string(),
+            error,
+        };
+        let hand = options
+            .limits
+            .parse_hand(hand_match.hand)
+            .map_err(render_error)?;
+        let rendered = RenderRequest {
+            style: options.style,
+            format: options.format,
+            strict: options.strict,
+            limits: options.limits,
+            ..RenderRequest::new(hand.clone())
+        }
+        .render(options.tile_set)
+        .map_err(render_error)?;
+        if !rendered.warnings.is_empty() {
+            warnings.push(DocumentWarning {
+                line: hand_match.line,
+                hand: hand_match.hand.to_string(),
+                warnings: rendered.warnings.clone(),
+            });
+        }
+
+        let url = match options.images {
+            ImageOutput::Assets { dir, url } => {
+                let file_name = write_asset(dir, &rendered.data, options.format)?;
+                images.push(dir.join(&file_name));
+                asset_url(url, &file_name)
+            }
+            ImageOutput::Embedded => format!(
+                "data:{};base64,{}",
+                options.format.mime_type(),
+                base64::engine::general_purpose::STANDARD.encode(&rendered.data)
+            ),
+        };
+        let link = format!("![{}]({url})", alt_text(&hand));
+        links.insert(
+            hand_match.hand.to_string(),
+            (link.clone(), rendered.warnings),
+        );
+        Ok(link)
+    })?;
+
+    Ok(ProcessedMarkdown {
+        markdown,
+        images,
+        warnings,
+    })
+}
+
+/// Writes an image to the assets directory, unless it's already there, and
+/// returns its file name.
+fn write_asset(
+    assets_dir: &Path,
+    data: &[u8],
+    format: OutputFormat,
+) -> Result<String, MarkdownError> {
+    let hash: String = Sha256::digest(data)
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect();
+    let file_name = format!("hand-{}.{}", &hash[..ASSET_HASH_LEN], format.extension());
+    let path = assets_dir.join(&file_name);
+    if !path.exists() {
+        std::fs::create_dir_all(assets_dir)
+            .and_then(|()| std::fs::write(&path, data))
+            .map_err(|error| MarkdownError::Write(path, error))?;
+    }
+
+    Ok(file_name)
+}
+
+fn asset_url(assets_url: &str, file_name: &str) -> String {
+    let assets_url = assets_url.trim_end_matches('/');
+    if assets_url.is_empty() {
+        file_name.to_string()
+    } else {
+        format!("{assets_url}/{file_name}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chombo_gen_render::tile_sets::built_in_tile_set;
+
+    use super::{ImageOutput, MarkdownOptions, process_markdown};
+
+    #[test]
+    fn repeated_hands_have_warnings_on_every_line() {
+        let tile_set = built_in_tile_set("Yellow").expect("built-in tile set");
+        let options = MarkdownOptions::new(tile_set.tile_set(), ImageOutput::Embedded);
+
+        let processed = process_markdown(
+            "{hand:123m} {hand:123m}\n```hand\n123m\n```\nText\n{hand:123m}\n",
+            &options,
+        )
+        .expect("hands can be rendered");
+
+        let lines: Vec<_> = processed
+            .warnings
+            .iter()
+            .map(|warning| (warning.line, warning.hand.as_str()))
+            .collect();
+        assert_eq!(lines, [(1, "123m"), (1, "123m"), (3, "123m"), (6, "123m")]);
+        assert!(
+            processed
+                .warnings
+                .iter()
+                .all(|warning| warning.warnings == processed.warnings[0].warnings)
+        );
+    }
+}
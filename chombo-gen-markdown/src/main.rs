@@ -0,0 +1,152 @@
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use chombo_gen_markdown::{ImageOutput, MarkdownOptions, process_markdown};
+use chombo_gen_render::cli::{format_arg, output_format, strict, style_args, tile_style};
+use chombo_gen_render::lint::warnings_summary;
+use chombo_gen_render::tile_sets::{BUILT_IN_TILE_SETS, built_in_tile_set};
+use clap::builder::PossibleValuesParser;
+use clap::{Arg, ArgMatches, Command, value_parser};
+
+const INPUT_PARAM: &str = "input";
+const OUTPUT_PARAM: &str = "output";
+const ASSETS_DIR_PARAM: &str = "assets-dir";
+const ASSETS_URL_PARAM: &str = "assets-url";
+const TILE_SET_PARAM: &str = "tile-set";
+
+const STDIO_PATH: &str = "-";
+
+fn command() -> Command {
+    Command::new(env!("CARGO_PKG_NAME"))
+        .version(env!("CARGO_PKG_VERSION"))
+        .about("Replace mahjong hands in a Markdown document with images")
+        .arg(
+            Arg::new(INPUT_PARAM)
+                .help("Markdown document, or `-` for the standard input")
+                .default_value(STDIO_PATH)
+                .value_parser(value_parser!(PathBuf)),
+        )
+        .arg(
+            Arg::new(OUTPUT_PARAM)
+                .help("File to write the document to, or `-` for the standard output")
+                .short('o')
+                .long(OUTPUT_PARAM)
+                .default_value(STDIO_PATH)
+                .value_parser(value_parser!(PathBuf)),
+        )
+        .arg(
+            Arg::new(ASSETS_DIR_PARAM)
+                .help("Directory to write the images to")
+                .short('a')
+                .long(ASSETS_DIR_PARAM)
+                .default_value("assets")
+                .value_parser(value_parser!(PathBuf)),
+        )
+        .arg(
+            Arg::new(ASSETS_URL_PARAM)
+                .help(
+                    "Path or URL of the assets directory in the image links; the assets \
+                     directory by default",
+                )
+                .long(ASSETS_URL_PARAM),
+        )
+        .arg(
+            Arg::new(TILE_SET_PARAM)
+                .help("Tile set to render the hands with")
+                .short('t')
+                .long(TILE_SET_PARAM)
+                .default_value("Yellow")
+                .value_parser(PossibleValuesParser::new(
+                    BUILT_IN_TILE_SETS.iter().map(|tile_set| tile_set.id),
+                )),
+        )
+        .arg(format_arg(&[]))
+        .args(style_args())
+}
+
+fn main() -> ExitCode {
+    match run(&command().get_matches()) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            eprintln!("error: {error}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(matches: &ArgMatches) -> Result<(), String> {
+    let input = matches
+        .get_one::<PathBuf>(INPUT_PARAM)
+        .expect("default provided");
+    let output = matches
+        .get_one::<PathBuf>(OUTPUT_PARAM)
+        .expect("default provided");
+    let assets_dir = matches
+        .get_one::<PathBuf>(ASSETS_DIR_PARAM)
+        .expect("default provided");
+    let assets_url = matches
+        .get_one::<String>(ASSETS_URL_PARAM)
+        .cloned()
+        .unwrap_or_else(|| assets_dir.to_string_lossy().replace('\\', "/"));
+    let tile_set = built_in_tile_set(
+        matches
+            .get_one::<String>(TILE_SET_PARAM)
+            .expect("default provided"),
+    )
+    .expect("validated by the parser");
+
+    let options = MarkdownOptions {
+        style: tile_style(matches)?,
+        format: output_format(matches).expect("validated by the parser"),
+        strict: strict(matches),
+        ..MarkdownOptions::new(
+            tile_set.tile_set(),
+            ImageOutput::Assets {
+                dir: assets_dir,
+                url: &assets_url,
+            },
+        )
+    };
+    let processed = process_markdown(&read_input(input)?, &options)
+        .map_err(|error| format!("{}: {error}", display_path(input)))?;
+    for warning in &processed.warnings {
+        eprintln!(
+            "warning: {}: line {}: {}: {}",
+            display_path(input),
+            warning.line,
+            warning.hand,
+            warnings_summary(&warning.warnings)
+        );
+    }
+
+    write_output(output, &processed.markdown)
+}
+
+fn read_input(path: &Path) -> Result<String, String> {
+    let mut content = String::new();
+    let result = if path == Path::new(STDIO_PATH) {
+        std::io::stdin().read_to_string(&mut content).map(|_| ())
+    } else {
+        std::fs::read_to_string(path).map(|file_content| content = file_content)
+    };
+    result.map_err(|error| format!("could not read {}: {error}", display_path(path)))?;
+    Ok(content)
+}
+
+fn write_output(path: &Path, content: &str) -> Result<(), String> {
+    let result = if path == Path::new(STDIO_PATH) {
+        std::io::stdout().write_all(content.as_bytes())
+    } else {
+        std::fs::write(path, content)
+    };
+    result.map_err(|error| format!("could not write {}: {error}", path.display()))
+}
+
+fn display_path(path: &Path) -> String {
+    if path == Path::new(STDIO_PATH) {
+        "<stdin>".to_string()
+    } else {
+        path.display().to_string()
+    }
+}
@@ -0,0 +1,401 @@
+/// Info string of the fenced code blocks containing hands.
+pub const HAND_LANGUAGE: &str = "hand";
+const SHORTCODE_PREFIX: &[u8] = b"{hand:";
+const SHORTCODE_SUFFIX: char = '}';
+const MIN_FENCE_LEN: usize = 3;
+/// Indentation, in columns, starting an indented code block; fences can't be
+/// indented this much.
+const CODE_INDENT: usize = 4;
+const TAB_WIDTH: usize = 4;
+
+/// Where a hand was found in a document.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum HandSource {
+    /// A line of a fenced code block with the `hand` info string.
+    Block,
+    /// An inline `{hand:...}` shortcode.
+    Shortcode,
+}
+
+/// A hand found in a Markdown document.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct HandMatch<'a> {
+    /// The hand description, without surrounding whitespace.
+    pub hand: &'a str,
+    /// Number of the line the hand is on, starting at 1.
+    pub line: usize,
+    pub source: HandSource,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Fence {
+    marker: u8,
+    len: usize,
+    indent: usize,
+    is_hand: bool,
+}
+
+impl Fence {
+    /// Parses the opening line of a fenced code block.
+    fn open(line: &str) -> Option<Self> {
+        let line = line.trim_end_matches(['\n', '\r']);
+        let trimmed = line.trim_start_matches(' ');
+        let indent = line.len() - trimmed.len();
+        if indent >= CODE_INDENT {
+            return None;
+        }
+        let marker = *trimmed.as_bytes().first()?;
+        if marker != b'`' && marker != b'~' {
+            return None;
+        }
+        let len = marker_run_len(trimmed.as_bytes(), 0, marker);
+        let info = trimmed[len..].trim();
+        if len < MIN_FENCE_LEN || (marker == b'`' && info.contains('`')) {
+            return None;
+        }
+
+        Some(Self {
+            marker,
+            len,
+            indent,
+            is_hand: info.split_whitespace().next() == Some(HAND_LANGUAGE),
+        })
+    }
+
+    fn is_closed_by(&self, line: &str) -> bool {
+        let trimmed = line.trim();
+        let len = marker_run_len(trimmed.as_bytes(), 0, self.marker);
+        indent_width(line) < CODE_INDENT && len >= self.len && trimmed[len..].trim().is_empty()
+    }
+}
+
+/// Returns the width of the indentation of a line, in columns.
+fn indent_width(line: &str) -> usize {
+    let mut width = 0;
+    for byte in line.bytes() {
+        match byte {
+            b' ' => width += 1,
+            b'\t' => width += TAB_WIDTH - width % TAB_WIDTH,
+            _ => break,
+        }
+    }
+    width
+}
+
+fn marker_run_len(bytes: &[u8], start: usize, marker: u8) -> usize {
+    bytes[start..]
+        .iter()
+        .take_while(|&&byte| byte == marker)
+        .count()
+}
+
+/// Replaces the hands in a Markdown document with the text returned by
+/// `replace`, e.g. image links.
+///
+/// Hands are taken from fenced code blocks with the `hand` info string (one
+/// hand per line; the whole block is replaced with the results separated by
+/// blank lines) and from inline `{hand:...}` shortcodes. Shortcodes in code
+/// spans and other code blocks, including indented ones, are left untouched.
+pub fn replace_hands<E>(
+    markdown: &str,
+    mut replace: impl FnMut(&HandMatch<'_>) -> Result<String, E>,
+) -> Result<String, E> {
+    let mut output = String::with_capacity(markdown.len());
+    let mut fence: Option<Fence> = None;
+    let mut block = Vec::new();
+    // Set after a hand block, so that it's not joined with the next paragraph.
+    let mut block_end: Option<&str> = None;
+    // Indented lines continue a paragraph instead of starting a code block.
+    let mut in_paragraph = false;
+
+    for (index, line) in markdown.split_inclusive('\n').enumerate() {
+        let line_number = index + 1;
+        if let Some(line_ending) = block_end.take()
+            && !line.trim().is_empty()
+        {
+            output.push_str(line_ending);
+        }
+        match fence {
+            Some(current) if current.is_closed_by(line) => {
+                fence = None;
+                in_paragraph = false;
+                if current.is_hand {
+                    write_block(&mut output, current, &block, line_ending(line));
+                    if !block.is_empty() {
+                        block_end = Some(line_ending(line));
+                    }
+                    block.clear();
+                } else {
+                    output.push_str(line);
+                }
+            }
+            Some(current) if current.is_hand => {
+                let hand = line.trim();
+                if !hand.is_empty() {
+                    block.push(replace(&HandMatch {
+                        hand,
+                        line: line_number,
+                        source: HandSource::Block,
+                    })?);
+                }
+            }
+            Some(_) => output.push_str(line),
+            None if !in_paragraph && indent_width(line) >= CODE_INDENT => {
+                // A line of an indented code block
+                output.push_str(line);
+            }
+            None => {
+                fence = Fence::open(line);
+                if fence.is_some_and(|fence| fence.is_hand) {
+                    block.clear();
+                } else if fence.is_some() {
+                    output.push_str(line);
+                } else {
+                    replace_shortcodes(&mut output, line, line_number, &mut replace)?;
+                    in_paragraph = !line.trim().is_empty();
+                }
+            }
+        }
+    }
+
+    // An unclosed code block extends to the end of the document.
+    if let Some(current) = fence
+        && current.is_hand
+    {
+        write_block(&mut output, current, &block, "\n");
+    }
+
+    Ok(output)
+}
+
+fn line_ending(line: &str) -> &str {
+    if line.ends_with("\r\n") {
+        "\r\n"
+    } else if line.ends_with('\n') {
+        "\n"
+    } else {
+        ""
+    }
+}
+
+/// Writes the replacements of the hands in a block as separate paragraphs,
+/// with the indentation of the block (e.g. in a list item).
+fn write_block(output: &mut String, fence: Fence, replacements: &[String], line_ending: &str) {
+    if replacements.is_empty() {
+        return;
+    }
+    let line_ending = if line_ending.is_empty() {
+        "\n"
+    } else {
+        line_ending
+    };
+    let indent = " ".repeat(fence.indent);
+    if !output.is_empty() && !output.ends_with(&line_ending.repeat(2)) {
+        output.push_str(line_ending);
+    }
+    for (index, replacement) in replacements.iter().enumerate() {
+        if index > 0 {
+            output.push_str(line_ending);
+        }
+        output.push_str(&indent);
+        output.push_str(replacement);
+        output.push_str(line_ending);
+    }
+}
+
+fn replace_shortcodes<E>(
+    output: &mut String,
+    line: &str,
+    line_number: usize,
+    replace: &mut impl FnMut(&HandMatch<'_>) -> Result<String, E>,
+) -> Result<(), E> {
+    let bytes = line.as_bytes();
+    let mut copied = 0;
+    let mut index = 0;
+
+    while index < bytes.len() {
+        if bytes[index] == b'`' {
+            let run = marker_run_len(bytes, index, b'`');
+            index = match closing_backticks(bytes, index + run, run) {
+                Some(end) => end + run,
+                None => index + run,
+            };
+            continue;
+        }
+
+        if bytes[index..].starts_with(SHORTCODE_PREFIX) {
+            let start = index + SHORTCODE_PREFIX.len();
+            if let Some(len) = line[start..].find(SHORTCODE_SUFFIX) {
+                output.push_str(&line[copied..index]);
+                output.push_str(&replace(&HandMatch {
+                    hand: line[start..start + len].trim(),
+                    line: line_number,
+                    source: HandSource::Shortcode,
+                })?);
+                index = start + len + SHORTCODE_SUFFIX.len_utf8();
+                copied = index;
+                continue;
+            }
+        }
+
+        index += 1;
+    }
+
+    output.push_str(&line[copied..]);
+    Ok(())
+}
+
+/// Returns the position of the run of exactly `len` backticks closing a code
+/// span.
+fn closing_backticks(bytes: &[u8], start: usize, len: usize) -> Option<usize> {
+    let mut index = start;
+    while index < bytes.len() {
+        if bytes[index] == b'`' {
+            let run = marker_run_len(bytes, index, b'`');
+            if run == len {
+                return Some(index);
+            }
+            index += run;
+        } else {
+            index += 1;
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::Infallible;
+
+    use super::{HandMatch, HandSource, replace_hands};
+
+    fn replace(markdown: &str) -> String {
+        replace_hands(markdown, |hand_match| {
+            Ok::<_, Infallible>(format!("[{}]", hand_match.hand))
+        })
+        .expect("replacing never fails")
+    }
+
+    fn hands(markdown: &str) -> Vec<(String, usize, HandSource)> {
+        let mut hands = Vec::new();
+        replace_hands(markdown, |hand_match: &HandMatch<'_>| {
+            hands.push((
+                hand_match.hand.to_string(),
+                hand_match.line,
+                hand_match.source,
+            ));
+            Ok::<_, Infallible>(String::new())
+        })
+        .expect("replacing never fails");
+        hands
+    }
+
+    #[test]
+    fn hand_block() {
+        assert_eq!(
+            replace("Hands:\n```hand\n123m\n\n 456p \n```\nAfter\n"),
+            "Hands:\n\n[123m]\n\n[456p]\n\nAfter\n"
+        );
+        assert_eq!(
+            hands("```hand\n123m\n456p\n```\n{hand:789s}\n"),
+            [
+                ("123m".to_string(), 2, HandSource::Block),
+                ("456p".to_string(), 3, HandSource::Block),
+                ("789s".to_string(), 5, HandSource::Shortcode),
+            ]
+        );
+    }
+
+    #[test]
+    fn tilde_fence() {
+        assert_eq!(replace("~~~ hand\n123m\n~~~\n"), "[123m]\n");
+        assert_eq!(replace("~~~\n{hand:1m}\n~~~\n"), "~~~\n{hand:1m}\n~~~\n");
+    }
+
+    #[test]
+    fn nested_fences() {
+        let markdown = "````markdown\n```hand\n123m\n```\n{hand:1m}\n````\n{hand:2m}\n";
+        assert_eq!(
+            replace(markdown),
+            "````markdown\n```hand\n123m\n```\n{hand:1m}\n````\n[2m]\n"
+        );
+    }
+
+    #[test]
+    fn mismatched_fences() {
+        // A block is only closed by a fence of the same marker, at least as
+        // long as the opening one
+        let markdown = "~~~\n{hand:1m}\n```\n{hand:2m}\n~~~~\n{hand:3m}\n";
+        assert_eq!(
+            replace(markdown),
+            "~~~\n{hand:1m}\n```\n{hand:2m}\n~~~~\n[3m]\n"
+        );
+        assert_eq!(
+            replace("````\n{hand:1m}\n```\n{hand:2m}\n````\n{hand:3m}\n"),
+            "````\n{hand:1m}\n```\n{hand:2m}\n````\n[3m]\n"
+        );
+        // Backtick fences can't have backticks in the info string
+        assert_eq!(replace("``` hand`\n{hand:1m}\n"), "``` hand`\n[1m]\n");
+    }
+
+    #[test]
+    fn indented_fence_is_not_a_fence() {
+        let markdown = "Text\n\n    ```hand\n    123m\n    ```\n";
+        assert_eq!(replace(markdown), markdown);
+        assert_eq!(replace("   ```hand\n   123m\n   ```\n"), "   [123m]\n");
+        // A closing fence can't be indented by 4 spaces either
+        assert_eq!(
+            replace("```\n    ```\n{hand:1m}\n```\n{hand:2m}\n"),
+            "```\n    ```\n{hand:1m}\n```\n[2m]\n"
+        );
+    }
+
+    #[test]
+    fn indented_code_block() {
+        let markdown = "Text\n\n    {hand:1m}\n\n    {hand:2m}\n\t{hand:3m}\nText {hand:4m}\n";
+        assert_eq!(
+            replace(markdown),
+            "Text\n\n    {hand:1m}\n\n    {hand:2m}\n\t{hand:3m}\nText [4m]\n"
+        );
+        assert_eq!(replace("    {hand:1m}\n"), "    {hand:1m}\n");
+        // Indented lines continuing a paragraph are not code
+        assert_eq!(replace("Text\n    {hand:1m}\n"), "Text\n    [1m]\n");
+        assert_eq!(
+            replace("```\ncode\n```\n    {hand:1m}\n"),
+            "```\ncode\n```\n    {hand:1m}\n"
+        );
+    }
+
+    #[test]
+    fn code_spans() {
+        assert_eq!(
+            replace("`{hand:1m}`, ``{hand:2m} ` x``, {hand:3m}\n"),
+            "`{hand:1m}`, ``{hand:2m} ` x``, [3m]\n"
+        );
+        // Backticks without a matching closing run are literal
+        assert_eq!(replace("`` {hand:1m} `\n"), "`` [1m] `\n");
+        assert_eq!(replace("{hand:1m} `{hand:2m}"), "[1m] `[2m]");
+    }
+
+    #[test]
+    fn unclosed_block_at_end_of_file() {
+        assert_eq!(
+            replace("Text\n```hand\n123m\n456p"),
+            "Text\n\n[123m]\n\n[456p]\n"
+        );
+        assert_eq!(replace("```\n{hand:1m}\n"), "```\n{hand:1m}\n");
+        assert_eq!(replace("```hand\n"), "");
+    }
+
+    #[test]
+    fn crlf_line_endings() {
+        assert_eq!(
+            replace("Text\r\n```hand\r\n123m\r\n456p\r\n```\r\nAfter {hand:1m}\r\n"),
+            "Text\r\n\r\n[123m]\r\n\r\n[456p]\r\n\r\nAfter [1m]\r\n"
+        );
+        assert_eq!(
+            replace("~~~\r\n{hand:1m}\r\n~~~\r\n{hand:2m}\r\n"),
+            "~~~\r\n{hand:1m}\r\n~~~\r\n[2m]\r\n"
+        );
+    }
+}
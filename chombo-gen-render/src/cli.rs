@@ -0,0 +1,80 @@
+//! Command line options shared by the tools rendering hands, so that they
+//! accept the same values as the query parameters of the API.
+
+use chombo_gen_common::enums::EnumName;
+use chombo_gen_common::theme::TileTheme;
+use clap::builder::PossibleValuesParser;
+use clap::{Arg, ArgAction, ArgMatches};
+
+use crate::OutputFormat;
+use crate::tile_theme::TileStyle;
+
+pub const FORMAT_ARG: &str = "format";
+pub const THEME_ARG: &str = "theme";
+pub const BACK_COLOR_ARG: &str = "back-color";
+pub const SCALE_ARG: &str = "scale";
+pub const STRICT_ARG: &str = "strict";
+
+/// Returns the `--format` option accepting the image formats, and the
+/// `extra_formats` of the tool.
+#[must_use]
+pub fn format_arg(extra_formats: &[&'static str]) -> Arg {
+    let formats = OutputFormat::ALL
+        .iter()
+        .map(|format| format.extension())
+        .chain(extra_formats.iter().copied());
+    Arg::new(FORMAT_ARG)
+        .help("Image format")
+        .short('f')
+        .long(FORMAT_ARG)
+        .default_value(OutputFormat::default().extension())
+        .value_parser(PossibleValuesParser::new(formats))
+}
+
+/// Returns the image format chosen with [`format_arg`], or `None` if it is
+/// one of the extra formats.
+#[must_use]
+pub fn output_format(matches: &ArgMatches) -> Option<OutputFormat> {
+    OutputFormat::from_extension(
+        matches
+            .get_one::<String>(FORMAT_ARG)
+            .expect("default provided"),
+    )
+}
+
+/// Returns the `--theme`, `--back-color`, `--scale` and `--strict` options.
+#[must_use]
+pub fn style_args() -> [Arg; 4] {
+    [
+        Arg::new(THEME_ARG)
+            .help("Colours of the tile faces")
+            .long(THEME_ARG)
+            .value_parser(PossibleValuesParser::new(
+                TileTheme::ALL.iter().map(|theme| theme.name()),
+            )),
+        Arg::new(BACK_COLOR_ARG)
+            .help("Colour of the back of face-down tiles, as #rrggbb")
+            .long(BACK_COLOR_ARG),
+        Arg::new(SCALE_ARG)
+            .help("Factor the tiles are enlarged by")
+            .long(SCALE_ARG),
+        Arg::new(STRICT_ARG)
+            .help("Fail on hands with warnings, e.g. too many copies of a tile")
+            .long(STRICT_ARG)
+            .action(ArgAction::SetTrue),
+    ]
+}
+
+/// Parses the options of [`style_args`] the same way as the query parameters
+/// of the API.
+pub fn tile_style(matches: &ArgMatches) -> Result<TileStyle, String> {
+    let value = |arg| matches.get_one::<String>(arg).map(String::as_str);
+    TileStyle::from_options(value(THEME_ARG), value(BACK_COLOR_ARG), value(SCALE_ARG))
+        .map_err(|error| format!("invalid tile style: {error}"))
+}
+
+/// Returns whether `--strict` is set.
+#[must_use]
+pub fn strict(matches: &ArgMatches) -> bool {
+    matches.get_flag(STRICT_ARG)
+}
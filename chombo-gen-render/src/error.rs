@@ -0,0 +1,111 @@
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+
+use chombo_gen_common::hand::HandWarning;
+use image::ImageError;
+use riichi_hand::parser::HandParseError;
+use riichi_hand::raster_renderer::HandRenderError;
+
+use crate::lint::warnings_summary;
+
+/// Why a hand could not be rendered.
+#[derive(Debug)]
+pub enum RenderError {
+    /// The hand description is longer than allowed by the limits.
+    HandTooLong {
+        length: usize,
+        max_length: usize,
+    },
+    Parse(HandParseError),
+    /// The hand has lint warnings, and strict mode is enabled.
+    InvalidHand(Vec<HandWarning>),
+    /// The image would have more pixels than allowed by the limits.
+    ImageTooLarge {
+        width: u32,
+        height: u32,
+        max_pixels: u64,
+    },
+    /// The hands of a print sheet would have more pixels in total than
+    /// allowed by the sheet.
+    SheetTooLarge {
+        pixels: u64,
+        max_pixels: u64,
+    },
+    Render(HandRenderError),
+    Encode(ImageError),
+}
+
+impl Display for RenderError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::HandTooLong { length, max_length } => {
+                write!(
+                    f,
+                    "Maximum hand description length exceeded ({length}/{max_length} characters)"
+                )
+            }
+            Self::Parse(error) => {
+                write!(f, "{error}")
+            }
+            Self::InvalidHand(warnings) => {
+                write!(f, "Hand is not valid: {}", warnings_summary(warnings))
+            }
+            Self::ImageTooLarge {
+                width,
+                height,
+                max_pixels,
+            } => {
+                write!(
+                    f,
+                    "Output image would be too large ({width}x{height} pixels, at most \
+                     {max_pixels} pixels are allowed)"
+                )
+            }
+            Self::SheetTooLarge { pixels, max_pixels } => {
+                write!(
+                    f,
+                    "Print sheet would be too large ({pixels} pixels of hand images, at most \
+                     {max_pixels} pixels are allowed)"
+                )
+            }
+            Self::Render(error) => {
+                write!(f, "{error}")
+            }
+            Self::Encode(error) => {
+                write!(f, "{error}")
+            }
+        }
+    }
+}
+
+impl Error for RenderError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Parse(error) => Some(error),
+            Self::Render(error) => Some(error),
+            Self::Encode(error) => Some(error),
+            Self::HandTooLong { .. }
+            | Self::InvalidHand(_)
+            | Self::ImageTooLarge { .. }
+            | Self::SheetTooLarge { .. } => None,
+        }
+    }
+}
+
+impl From<HandParseError> for RenderError {
+    fn from(error: HandParseError) -> Self {
+        Self::Parse(error)
+    }
+}
+
+impl From<HandRenderError> for RenderError {
+    fn from(error: HandRenderError) -> Self {
+        Self::Render(error)
+    }
+}
+
+impl From<ImageError> for RenderError {
+    fn from(error: ImageError) -> Self {
+        Self::Encode(error)
+    }
+}
@@ -0,0 +1,136 @@
+use std::collections::BTreeMap;
+
+use chombo_gen_common::hand::HandWarning;
+use riichi_hand::raster_renderer::{HandRenderError, TileSet as RasterTileSet};
+use sha2::{Digest, Sha256};
+
+use crate::error::RenderError;
+use crate::normalize::normalize;
+use crate::render::{OutputFormat, RenderRequest, encode_image};
+use crate::tile_sets::tile_asset;
+
+/// Number of hex digits of the image hash used in the tile file names.
+const ASSET_HASH_LEN: usize = 16;
+
+/// Options of the LaTeX export.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LatexOptions {
+    /// Width of a tile in the document, in millimetres.
+    pub tile_width_mm: f64,
+    /// Path of the directory with the tile images, as used in
+    /// `\includegraphics`.
+    pub assets_path: String,
+}
+
+impl Default for LatexOptions {
+    fn default() -> Self {
+        Self {
+            tile_width_mm: 8.0,
+            assets_path: "tiles".to_string(),
+        }
+    }
+}
+
+/// PNG image of a single tile, referenced by the LaTeX snippets.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TileAsset {
+    /// Name of the tile followed by the hash of the image, e.g.
+    /// `Man5-Dora-0123456789abcdef.png`, so that tiles drawn with different
+    /// tile sets or styles don't overwrite each other.
+    pub file_name: String,
+    pub data: Vec<u8>,
+}
+
+#[derive(Debug, Clone)]
+pub struct LatexHand {
+    /// A `tikzpicture` environment; the document needs to load the `tikz`
+    /// package.
+    pub snippet: String,
+    /// Images of the tiles used by the snippet, each one once.
+    pub assets: Vec<TileAsset>,
+    pub warnings: Vec<HandWarning>,
+}
+
+impl RenderRequest {
+    /// Exports the hand as a TikZ picture placing the images of single tiles
+    /// the same way as they are placed in the rendered images, including
+    /// rotated and shifted tiles and the gaps between groups.
+    ///
+    /// The tile images are always PNG files, since pdfLaTeX can't read WebP.
+    pub fn render_latex(
+        &self,
+        tile_set: &dyn RasterTileSet,
+        options: &LatexOptions,
+    ) -> Result<LatexHand, RenderError> {
+        let warnings = self.check(tile_set)?;
+        let styled_tile_set = self.styled_tile_set(tile_set);
+        let layout = self.layout(tile_set);
+        let mm_per_pixel = options.tile_width_mm / f64::from(styled_tile_set.tile_width());
+        let mm = |pixels: u32| format_decimal(f64::from(pixels) * mm_per_pixel);
+        let assets_path = options.assets_path.trim_end_matches('/');
+
+        let mut assets = BTreeMap::new();
+        let mut snippet = format!(
+            "% {}\n\\begin{{tikzpicture}}[x=1mm, y=-1mm, baseline={{(0,{height})}}]\n  \
+             \\useasboundingbox (0,0) rectangle ({},{height});\n",
+            normalize(&self.hand, false),
+            mm(layout.width),
+            height = mm(layout.height),
+        );
+        for tile in &layout.tiles {
+            let (asset_tile, asset_name) = tile_asset(tile.tile);
+            if !assets.contains_key(&asset_name) {
+                let image = styled_tile_set
+                    .tile_image(&asset_tile)
+                    .map_err(HandRenderError::from)?;
+                let data = encode_image(image, OutputFormat::Png)?;
+                let file_name = format!("{asset_name}-{}.png", content_hash(&data));
+                assets.insert(asset_name.clone(), TileAsset { file_name, data });
+            }
+            let file_name = &assets[&asset_name].file_name;
+
+            snippet.push_str(&format!(
+                "  \\node[anchor=north west, inner sep=0pt] at ({},{}) \
+                 {{\\includegraphics[width={}mm, height={}mm]{{{}}}}};\n",
+                mm(tile.x),
+                mm(tile.y),
+                mm(tile.width),
+                mm(tile.height),
+                asset_path(assets_path, file_name),
+            ));
+        }
+        snippet.push_str("\\end{tikzpicture}\n");
+
+        Ok(LatexHand {
+            snippet,
+            assets: assets.into_values().collect(),
+            warnings,
+        })
+    }
+}
+
+fn content_hash(data: &[u8]) -> String {
+    Sha256::digest(data)
+        .iter()
+        .take(ASSET_HASH_LEN / 2)
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
+
+fn asset_path(assets_path: &str, file_name: &str) -> String {
+    if assets_path.is_empty() {
+        file_name.to_string()
+    } else {
+        format!("{assets_path}/{file_name}")
+    }
+}
+
+/// Formats a length with at most 3 decimal places, which is well below the
+/// precision of a printer.
+pub(crate) fn format_decimal(value: f64) -> String {
+    let formatted = format!("{value:.3}");
+    formatted
+        .trim_end_matches('0')
+        .trim_end_matches('.')
+        .to_string()
+}
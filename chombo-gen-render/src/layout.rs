@@ -0,0 +1,155 @@
+use riichi_hand::raster_renderer::RenderOptions;
+use riichi_hand::{Hand, HandTile, TilePlacement};
+
+/// Position of a single tile in a rendered hand, in pixels from the top left
+/// corner. `width` and `height` are the dimensions of the tile image, i.e.
+/// they are swapped for rotated tiles.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TileLayout {
+    pub tile: HandTile,
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HandLayout {
+    pub width: u32,
+    pub height: u32,
+    pub tiles: Vec<TileLayout>,
+}
+
+impl HandLayout {
+    #[must_use]
+    pub fn pixel_count(&self) -> u64 {
+        u64::from(self.width) * u64::from(self.height)
+    }
+}
+
+/// Computes where each tile of a hand is placed, the same way
+/// [`RasterRenderer`](riichi_hand::raster_renderer::RasterRenderer) does:
+/// groups are aligned to the bottom, and a rotated and shifted tile is placed
+/// on top of the rotated tile preceding it.
+pub fn layout_hand(
+    hand: &Hand,
+    tile_width: u32,
+    tile_height: u32,
+    options: RenderOptions,
+) -> HandLayout {
+    let tile_gap = (options.tile_gap.0 * tile_width as f32) as u32;
+    let group_gap = (options.group_gap.0 * tile_width as f32) as u32;
+    let slot_size = |placement| match placement {
+        TilePlacement::Normal => (tile_width, tile_height),
+        TilePlacement::Rotated => (tile_height, tile_width),
+        TilePlacement::RotatedAndShifted => (tile_height, 2 * tile_width),
+    };
+
+    // First pass: horizontal positions, relative to the bottom of the hand.
+    let mut tiles = Vec::new();
+    let mut width = 0;
+    let mut height = 0;
+    for (group_index, group) in hand.groups().iter().enumerate() {
+        if group_index > 0 {
+            width += group_gap;
+        }
+
+        let group_start = width;
+        let mut x = group_start;
+        let mut group_end = group_start;
+        let mut last_placement = TilePlacement::Normal;
+        for (tile_index, &tile) in group.iter().enumerate() {
+            let (slot_width, slot_height) = slot_size(tile.placement);
+            if tile_index > 0 {
+                x += tile_gap;
+            }
+            if last_placement == TilePlacement::Rotated
+                && tile.placement == TilePlacement::RotatedAndShifted
+            {
+                x -= slot_width + tile_gap;
+            }
+
+            let (image_width, image_height) = match tile.placement {
+                TilePlacement::Normal => (tile_width, tile_height),
+                TilePlacement::Rotated | TilePlacement::RotatedAndShifted => {
+                    (tile_height, tile_width)
+                }
+            };
+            tiles.push(TileLayout {
+                tile,
+                x,
+                y: slot_height,
+                width: image_width,
+                height: image_height,
+            });
+
+            height = height.max(slot_height);
+            x += slot_width;
+            group_end = group_end.max(x);
+            last_placement = tile.placement;
+        }
+        width = group_end;
+    }
+
+    // Second pass: convert the distances from the bottom into coordinates.
+    for tile in &mut tiles {
+        tile.y = height - tile.y;
+    }
+
+    HandLayout {
+        width,
+        height,
+        tiles,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use riichi_hand::parser::HandParser;
+    use riichi_hand::raster_renderer::{RasterRenderer, RenderOptions};
+
+    use super::layout_hand;
+    use crate::tile_sets::BUILT_IN_TILE_SETS;
+
+    const HANDS: [&str; 6] = ["123m456p789s11z", "123m__456p", "_123m", "123m_", "_", ""];
+
+    /// Hands with rotated and shifted tiles, which not every tile set supports.
+    const ROTATED_HANDS: [&str; 6] = [
+        "123m_2*22z",
+        "11*1**1m",
+        "1*1**11m_789s",
+        "1**11m",
+        "1*1*11m",
+        "11*1**1m_2*2**22z_33*3**3p",
+    ];
+
+    #[test]
+    fn layout_matches_raster_renderer() {
+        for built_in in &BUILT_IN_TILE_SETS {
+            let tile_set = built_in.tile_set();
+            let rotated_hands: &[&str] = if built_in.id == "MartinPersson" {
+                &[]
+            } else {
+                &ROTATED_HANDS
+            };
+            for hand in HANDS.iter().chain(rotated_hands) {
+                let parsed = HandParser::parse(hand).expect("test hands are valid");
+                let layout = layout_hand(
+                    &parsed,
+                    tile_set.tile_width(),
+                    tile_set.tile_height(),
+                    RenderOptions::default(),
+                );
+                let image = RasterRenderer::render(&parsed, &tile_set, RenderOptions::default())
+                    .expect("test hands can be rendered");
+
+                assert_eq!(
+                    (layout.width, layout.height),
+                    image.dimensions(),
+                    "hand `{hand}` with tile set {}",
+                    built_in.id
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,30 @@
+//! Rendering of Japanese (Riichi) Mahjong hands to images, as done by the
+//! ChomboGen backend.
+//!
+//! ```no_run
+//! use chombo_gen_render::tile_sets::built_in_tile_set;
+//! use chombo_gen_render::{RenderLimits, RenderRequest};
+//!
+//! let hand = RenderLimits::default().parse_hand("123m456p789s11z_222z")?;
+//! let tile_set = built_in_tile_set("Yellow").expect("built-in tile set");
+//! let image = RenderRequest::new(hand).render(tile_set.tile_set())?;
+//! std::fs::write("hand.png", image.data)?;
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+
+#[cfg(feature = "cli")]
+pub mod cli;
+mod error;
+pub mod latex;
+pub mod layout;
+pub mod lint;
+pub mod normalize;
+pub mod pdf;
+mod render;
+pub mod scaled_tile_set;
+pub mod tile_sets;
+pub mod tile_theme;
+pub mod vector_tile_set;
+
+pub use error::RenderError;
+pub use render::{OutputFormat, RenderLimits, RenderRequest, RenderedImage};
@@ -0,0 +1,224 @@
+use std::collections::BTreeMap;
+
+use chombo_gen_common::hand::{HandWarning, HandWarningKind};
+use riichi_hand::{Hand, HandTile, Suite, Tile, TilePlacement, TileValue};
+
+use crate::normalize::tile_notation;
+
+const MAX_TILE_COPIES: usize = 4;
+const MAX_RED_FIVES_PER_SUITE: usize = 1;
+const KAN_SIZE: usize = 4;
+const VALID_TILE_COUNTS: [usize; 2] = [13, 14];
+
+/// Checks a parsed hand for things that can't happen in an actual game.
+pub fn lint(hand: &Hand) -> Vec<HandWarning> {
+    let mut warnings = Vec::new();
+    check_tile_copies(hand, &mut warnings);
+    check_red_fives(hand, &mut warnings);
+    check_kans(hand, &mut warnings);
+    check_tile_count(hand, &mut warnings);
+    warnings
+}
+
+fn check_tile_copies(hand: &Hand, warnings: &mut Vec<HandWarning>) {
+    let mut counts: BTreeMap<Tile, usize> = BTreeMap::new();
+    for tile in hand.tiles().filter(|tile| tile.suite != Suite::Any) {
+        *counts.entry(without_red(tile)).or_default() += 1;
+    }
+
+    for (tile, count) in counts {
+        if count > MAX_TILE_COPIES {
+            warnings.push(HandWarning {
+                kind: HandWarningKind::TooManyCopies,
+                message: format!(
+                    "Tile {} appears {count} times (at most {MAX_TILE_COPIES} copies exist)",
+                    tile_notation(&tile)
+                ),
+            });
+        }
+    }
+}
+
+fn check_red_fives(hand: &Hand, warnings: &mut Vec<HandWarning>) {
+    let mut counts: BTreeMap<Suite, usize> = BTreeMap::new();
+    for tile in hand.tiles().filter(is_red_five) {
+        *counts.entry(tile.suite).or_default() += 1;
+    }
+
+    for (suite, count) in counts {
+        if count > MAX_RED_FIVES_PER_SUITE {
+            warnings.push(HandWarning {
+                kind: HandWarningKind::TooManyRedFives,
+                message: format!(
+                    "{count} red fives of {suite} (at most {MAX_RED_FIVES_PER_SUITE} expected)"
+                ),
+            });
+        }
+    }
+}
+
+fn check_kans(hand: &Hand, warnings: &mut Vec<HandWarning>) {
+    for (index, group) in hand.groups().iter().enumerate() {
+        if is_marked_as_kan(group) && group.len() != KAN_SIZE {
+            warnings.push(HandWarning {
+                kind: HandWarningKind::IncompleteKan,
+                message: format!(
+                    "Group {} looks like a kan, but consists of {} tiles instead of {KAN_SIZE}",
+                    index + 1,
+                    group.len()
+                ),
+            });
+        }
+    }
+}
+
+fn check_tile_count(hand: &Hand, warnings: &mut Vec<HandWarning>) {
+    let tile_count: usize = hand
+        .groups()
+        .iter()
+        .map(|group| if is_kan(group) { 3 } else { group.len() })
+        .sum();
+
+    if !VALID_TILE_COUNTS.contains(&tile_count) {
+        warnings.push(HandWarning {
+            kind: HandWarningKind::WrongTileCount,
+            message: format!(
+                "Hand consists of {tile_count} tiles (not counting the fourth tile of kans), \
+                 expected 13 or 14"
+            ),
+        });
+    }
+}
+
+/// Whether the group uses the notation specific to kans: a rotated and shifted
+/// tile (added kan), or face-down tiles (closed kan).
+fn is_marked_as_kan(group: &[HandTile]) -> bool {
+    let has_shifted = group
+        .iter()
+        .any(|tile| tile.placement == TilePlacement::RotatedAndShifted);
+    let has_face_down = group.iter().any(|tile| tile.tile.suite == Suite::Any);
+    let has_face_up = group.iter().any(|tile| tile.tile.suite != Suite::Any);
+
+    has_shifted || (has_face_down && has_face_up)
+}
+
+fn is_kan(group: &[HandTile]) -> bool {
+    if is_marked_as_kan(group) {
+        return true;
+    }
+
+    let mut face_up_tiles = group
+        .iter()
+        .filter(|tile| tile.tile.suite != Suite::Any)
+        .map(|tile| without_red(tile.tile));
+    let first_tile = face_up_tiles.next();
+    group.len() == KAN_SIZE
+        && first_tile.is_some()
+        && face_up_tiles.all(|tile| Some(tile) == first_tile)
+}
+
+fn is_red_five(tile: &Tile) -> bool {
+    tile.suite != Suite::Honor && tile.suite != Suite::Any && tile.value == TileValue(0)
+}
+
+fn without_red(tile: Tile) -> Tile {
+    if is_red_five(&tile) {
+        Tile {
+            suite: tile.suite,
+            value: TileValue(5),
+        }
+    } else {
+        tile
+    }
+}
+
+pub fn warnings_summary(warnings: &[HandWarning]) -> String {
+    warnings
+        .iter()
+        .map(|warning| warning.message.as_str())
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+#[cfg(test)]
+mod tests {
+    use riichi_hand::parser::HandParser;
+
+    use super::*;
+
+    fn warning_kinds(hand: &str) -> Vec<HandWarningKind> {
+        lint(&HandParser::parse(hand).expect("test hands are valid"))
+            .into_iter()
+            .map(|warning| warning.kind)
+            .collect()
+    }
+
+    #[test]
+    fn valid_hands_have_no_warnings() {
+        assert_eq!(warning_kinds("123m456p789s111z22z"), []);
+        assert_eq!(warning_kinds("123m456p789s1z222z"), []);
+        assert_eq!(warning_kinds("123m406p789s22z_5*55p"), []);
+    }
+
+    #[test]
+    fn too_many_copies() {
+        assert_eq!(
+            warning_kinds("11111m456p789s222z"),
+            [HandWarningKind::TooManyCopies]
+        );
+        // Red fives are copies of the fives.
+        assert_eq!(
+            warning_kinds("55550m456p789s222z"),
+            [HandWarningKind::TooManyCopies]
+        );
+    }
+
+    #[test]
+    fn too_many_red_fives() {
+        assert_eq!(
+            warning_kinds("00m456p789s11z222z"),
+            [HandWarningKind::TooManyRedFives]
+        );
+        assert_eq!(warning_kinds("0m0p0s456p789s11z22z"), []);
+    }
+
+    #[test]
+    fn wrong_tile_count() {
+        assert_eq!(
+            warning_kinds("11122233344455566677m"),
+            [HandWarningKind::WrongTileCount]
+        );
+        assert_eq!(warning_kinds("123m"), [HandWarningKind::WrongTileCount]);
+    }
+
+    #[test]
+    fn fourth_tile_of_kans_is_not_counted() {
+        assert_eq!(warning_kinds("123m456p789s22z_1111z"), []);
+        assert_eq!(warning_kinds("123m456p789s22z_?11?z"), []);
+        assert_eq!(warning_kinds("123m456p789s22z_11*1**1z"), []);
+    }
+
+    #[test]
+    fn incomplete_kan() {
+        assert_eq!(
+            warning_kinds("123m456p789s22z_1*1**1z"),
+            [HandWarningKind::IncompleteKan]
+        );
+        assert_eq!(
+            warning_kinds("123m456p789s22z_?1?z"),
+            [HandWarningKind::IncompleteKan]
+        );
+    }
+
+    #[test]
+    fn summary_joins_messages() {
+        let warnings = lint(&HandParser::parse("11111m00p").expect("test hands are valid"));
+
+        assert_eq!(
+            warnings_summary(&warnings),
+            "Tile 1m appears 5 times (at most 4 copies exist); 2 red fives of Pinzu (at most 1 \
+             expected); Hand consists of 7 tiles (not counting the fourth tile of kans), expected \
+             13 or 14"
+        );
+    }
+}
@@ -0,0 +1,178 @@
+use riichi_hand::{Hand, HandTile, Suite, Tile, TilePlacement};
+
+/// Returns the canonical notation of a hand.
+///
+/// Honors are always written with the `z` suite, and the suite character is
+/// only written once per run of tiles in the same suite. When `sort` is set,
+/// tiles in closed groups (no rotated or face-down tiles) are put in the
+/// standard order: manzu, pinzu, souzu, honors, with red fives next to fives.
+///
+/// Parsing the result yields the same hand as the input (unless sorted).
+pub fn normalize(hand: &Hand, sort: bool) -> String {
+    hand.groups()
+        .iter()
+        .map(|group| {
+            if sort && is_closed_group(group) {
+                let mut group = group.clone();
+                group.sort_by_key(tile_sort_key);
+                write_group(&group)
+            } else {
+                write_group(group)
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("_")
+}
+
+fn is_closed_group(group: &[HandTile]) -> bool {
+    group
+        .iter()
+        .all(|tile| tile.placement == TilePlacement::Normal && tile.tile.suite != Suite::Any)
+}
+
+fn tile_sort_key(hand_tile: &HandTile) -> (Suite, u8, bool) {
+    let value = hand_tile.tile.value.0;
+    let is_red = value == 0;
+    (hand_tile.tile.suite, if is_red { 5 } else { value }, is_red)
+}
+
+fn write_group(group: &[HandTile]) -> String {
+    let mut notation = String::new();
+    let mut run_suite = None;
+
+    for hand_tile in group {
+        let suite = hand_tile.tile.suite;
+        if let Some(current_suite) = run_suite
+            && current_suite != suite
+        {
+            notation.push(suite_char(current_suite));
+            run_suite = None;
+        }
+
+        if suite == Suite::Any {
+            notation.push('?');
+        } else {
+            notation.push(char::from(b'0' + hand_tile.tile.value.0));
+            run_suite = Some(suite);
+        }
+        notation.push_str(placement_modifier(hand_tile.placement));
+    }
+
+    if let Some(current_suite) = run_suite {
+        notation.push(suite_char(current_suite));
+    }
+
+    notation
+}
+
+/// Returns the notation of a single tile, e.g. `1m`, `0p` or `?`.
+pub fn tile_notation(tile: &Tile) -> String {
+    if tile.suite == Suite::Any {
+        "?".to_string()
+    } else {
+        format!("{}{}", tile.value, suite_char(tile.suite))
+    }
+}
+
+fn suite_char(suite: Suite) -> char {
+    match suite {
+        Suite::Manzu => 'm',
+        Suite::Pinzu => 'p',
+        Suite::Souzu => 's',
+        Suite::Honor => 'z',
+        Suite::Any => '?',
+    }
+}
+
+fn placement_modifier(placement: TilePlacement) -> &'static str {
+    match placement {
+        TilePlacement::Normal => "",
+        TilePlacement::Rotated => "*",
+        TilePlacement::RotatedAndShifted => "**",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use riichi_hand::parser::HandParser;
+
+    use super::*;
+
+    const HANDS: [&str; 10] = [
+        "123m456p789s11z",
+        "1s2s3s",
+        "ESWNwgr",
+        "505m_3*12m",
+        "11*1**1m_?55?z",
+        "1z9s1p1m",
+        "_123m",
+        "123m_",
+        "_",
+        "",
+    ];
+
+    fn normalized(hand: &str, sort: bool) -> String {
+        normalize(
+            &HandParser::parse(hand).expect("test hands are valid"),
+            sort,
+        )
+    }
+
+    #[test]
+    fn suites_are_written_once_per_run() {
+        assert_eq!(normalized("1s2s3s", false), "123s");
+        assert_eq!(normalized("1m2m3p4p5m", false), "12m34p5m");
+    }
+
+    #[test]
+    fn honors_use_z_suite() {
+        assert_eq!(normalized("E", false), "1z");
+        assert_eq!(normalized("E", false), normalized("1z", false));
+        assert_eq!(normalized("ESWNwgr", false), "1234567z");
+    }
+
+    #[test]
+    fn closed_groups_are_sorted() {
+        assert_eq!(normalized("1z9s1p1m", true), "1m1p9s1z");
+        assert_eq!(normalized("321m_987p", true), "123m_789p");
+    }
+
+    #[test]
+    fn red_fives_are_sorted_after_fives() {
+        assert_eq!(normalized("0m6m5m4m", true), "4506m");
+        assert_eq!(normalized("505p", true), "550p");
+    }
+
+    #[test]
+    fn open_groups_are_not_sorted() {
+        assert_eq!(normalized("321m_3*12m", true), "123m_3*12m");
+        assert_eq!(normalized("21**1m_5*05p", true), "21**1m_5*05p");
+        assert_eq!(normalized("?21?m", true), "?21m?");
+    }
+
+    #[test]
+    fn tiles_are_not_sorted_without_sort() {
+        assert_eq!(normalized("1z9s1p1m", false), "1z9s1p1m");
+    }
+
+    #[test]
+    fn normalized_hands_parse_to_same_hand() {
+        for hand in HANDS {
+            let parsed = HandParser::parse(hand).expect("test hands are valid");
+            let reparsed =
+                HandParser::parse(&normalize(&parsed, false)).expect("normalized hands are valid");
+
+            assert_eq!(reparsed.groups(), parsed.groups(), "hand `{hand}`");
+        }
+    }
+
+    #[test]
+    fn sorting_is_stable_across_round_trips() {
+        for hand in HANDS {
+            let sorted = normalized(hand, true);
+
+            assert_eq!(normalized(&sorted, true), sorted, "hand `{hand}`");
+            assert_eq!(normalized(&sorted, false), sorted, "hand `{hand}`");
+        }
+    }
+}
@@ -0,0 +1,981 @@
+use std::collections::HashMap;
+use std::io::Write;
+use std::time::Instant;
+
+use chombo_gen_common::hand::HandWarning;
+use flate2::Compression;
+use flate2::write::ZlibEncoder;
+use image::RgbaImage;
+use riichi_hand::raster_renderer::{HandRenderError, TileSet as RasterTileSet};
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+use crate::error::RenderError;
+use crate::latex::format_decimal;
+use crate::layout::HandLayout;
+use crate::render::{RenderLimits, RenderRequest, RenderedImage};
+use crate::tile_sets::tile_asset;
+use crate::tile_theme::TileStyle;
+use crate::vector_tile_set::{TileDrawing, VectorTileSet, opacity_state_name};
+
+const POINTS_PER_MM: f64 = 72.0 / 25.4;
+const SHEET_TITLE_FONT_SIZE: f64 = 16.0;
+const HAND_TITLE_FONT_SIZE: f64 = 10.0;
+/// Distance from the top of a line of text to its baseline, relative to the
+/// font size.
+const FONT_ASCENT: f64 = 0.8;
+/// Space between a hand and its title, in points.
+const TITLE_GAP: f64 = 4.0;
+const COLUMN_GAP_MM: f64 = 8.0;
+const ROW_GAP_MM: f64 = 10.0;
+
+/// Widths of the printable ASCII characters in Helvetica, in 1/1000 of the
+/// font size.
+const HELVETICA_WIDTHS: [u16; 95] = [
+    278, 278, 355, 556, 556, 889, 667, 191, 333, 333, 389, 584, 278, 333, 278,
+    278, // ' '..'/'
+    556, 556, 556, 556, 556, 556, 556, 556, 556, 556, // '0'..'9'
+    278, 278, 584, 584, 584, 556, 1015, // ':'..'@'
+    667, 667, 722, 722, 667, 611, 778, 722, 278, 500, 667, 556, 833, // 'A'..'M'
+    722, 778, 667, 778, 722, 667, 611, 722, 667, 944, 667, 667, 611, // 'N'..'Z'
+    278, 278, 278, 469, 556, 333, // '['..'`'
+    556, 556, 500, 556, 556, 278, 556, 556, 222, 222, 500, 222, 833, // 'a'..'m'
+    556, 556, 556, 556, 333, 500, 278, 556, 500, 722, 500, 500, 500, // 'n'..'z'
+    334, 260, 334, 584, // '{'..'~'
+];
+const DEFAULT_CHAR_WIDTH: u16 = 556;
+
+/// Paper size of print sheets.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum PaperSize {
+    #[default]
+    A4,
+    Letter,
+}
+
+impl PaperSize {
+    /// Returns the width and height of the paper, in points.
+    fn dimensions(self) -> (f64, f64) {
+        match self {
+            Self::A4 => (595.276, 841.89),
+            Self::Letter => (612.0, 792.0),
+        }
+    }
+}
+
+/// A hand on a [`PrintSheet`].
+#[derive(Debug, Clone)]
+pub struct SheetHand {
+    pub request: RenderRequest,
+    /// Text printed above the hand.
+    pub title: Option<String>,
+}
+
+/// Settings of a PDF document with many hands laid out in a grid, e.g. for
+/// printing quizzes.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PrintSheet {
+    pub paper: PaperSize,
+    pub columns: usize,
+    /// Margin on every side of the pages, in millimetres.
+    pub margin_mm: f64,
+    /// Width of a tile, in millimetres; hands too wide for their column are
+    /// shrunk to fit it.
+    pub tile_width_mm: f64,
+    /// Text printed at the top of the first page.
+    pub title: Option<String>,
+    /// Maximum number of pixels of the images of all the hands together,
+    /// like [`RenderLimits::max_output_pixels`] for a single hand.
+    pub max_output_pixels: u64,
+}
+
+impl Default for PrintSheet {
+    fn default() -> Self {
+        Self {
+            paper: PaperSize::default(),
+            columns: 2,
+            margin_mm: 15.0,
+            tile_width_mm: 8.0,
+            title: None,
+            max_output_pixels: RenderLimits::default().max_output_pixels,
+        }
+    }
+}
+
+impl PrintSheet {
+    /// Checks the hands with [`RenderRequest::check`] and lays them out in
+    /// rows of [`columns`](Self::columns) hands, on as many pages as needed.
+    ///
+    /// The tiles are drawn as vector paths if `vector_tile_set` is given,
+    /// like in [`RenderRequest::render_pdf`].
+    pub fn render(
+        &self,
+        hands: &[SheetHand],
+        tile_set: &dyn RasterTileSet,
+        vector_tile_set: Option<&VectorTileSet>,
+    ) -> Result<Vec<u8>, RenderError> {
+        for hand in hands {
+            hand.request.check(tile_set)?;
+        }
+        self.check_pixels(hands, tile_set)?;
+        self.render_checked(hands, tile_set, vector_tile_set)
+    }
+
+    /// Rejects sheets whose hands have more than
+    /// [`max_output_pixels`](Self::max_output_pixels) in total, which
+    /// [`render`](Self::render) does after checking every hand.
+    pub fn check_pixels(
+        &self,
+        hands: &[SheetHand],
+        tile_set: &dyn RasterTileSet,
+    ) -> Result<(), RenderError> {
+        let pixels = hands
+            .iter()
+            .map(|hand| hand.request.layout(tile_set).pixel_count())
+            .fold(0, u64::saturating_add);
+        if pixels > self.max_output_pixels {
+            return Err(RenderError::SheetTooLarge {
+                pixels,
+                max_pixels: self.max_output_pixels,
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Lays out hands already checked with [`RenderRequest::check`] and
+    /// [`check_pixels`](Self::check_pixels), e.g. to keep their warnings,
+    /// like [`render`](Self::render).
+    pub fn render_checked(
+        &self,
+        hands: &[SheetHand],
+        tile_set: &dyn RasterTileSet,
+        vector_tile_set: Option<&VectorTileSet>,
+    ) -> Result<Vec<u8>, RenderError> {
+        let (page_width, page_height) = self.paper.dimensions();
+        let margin = self.margin_mm * POINTS_PER_MM;
+        let columns = self.columns.max(1);
+        let column_gap = COLUMN_GAP_MM * POINTS_PER_MM;
+        let row_gap = ROW_GAP_MM * POINTS_PER_MM;
+        let column_width = ((page_width - 2.0 * margin - (columns - 1) as f64 * column_gap)
+            / columns as f64)
+            .max(1.0);
+
+        let mut document = PdfDocument::new(vector_tile_set);
+        let mut content = String::new();
+        let mut top = margin;
+        if let Some(title) = &self.title {
+            let title = fit_text(title, SHEET_TITLE_FONT_SIZE, page_width - 2.0 * margin);
+            draw_text(
+                &mut content,
+                &title,
+                SHEET_TITLE_FONT_SIZE,
+                margin,
+                page_height - top,
+            );
+            top += SHEET_TITLE_FONT_SIZE + row_gap;
+        }
+
+        let mut page_is_empty = true;
+        for row in hands.chunks(columns) {
+            let cells: Vec<_> = row
+                .iter()
+                .map(|hand| {
+                    let layout = hand.request.layout(tile_set);
+                    let tile_width = tile_set.tile_width() * hand.request.style.scale.factor();
+                    let mut scale = self.tile_width_mm * POINTS_PER_MM / f64::from(tile_width);
+                    if layout.width > 0 {
+                        scale = scale.min(column_width / f64::from(layout.width));
+                    }
+                    let title_height = if hand.title.is_some() {
+                        HAND_TITLE_FONT_SIZE + TITLE_GAP
+                    } else {
+                        0.0
+                    };
+                    let height = title_height + f64::from(layout.height) * scale;
+                    (layout, scale, height)
+                })
+                .collect();
+            let row_height = cells
+                .iter()
+                .map(|(_, _, height)| *height)
+                .fold(0.0, f64::max);
+
+            if !page_is_empty && top + row_height > page_height - margin {
+                document.add_page(page_width, page_height, std::mem::take(&mut content));
+                top = margin;
+            }
+
+            for (column, (hand, (layout, scale, _))) in row.iter().zip(&cells).enumerate() {
+                let left = margin + column as f64 * (column_width + column_gap);
+                let mut hand_top = top;
+                if let Some(title) = &hand.title {
+                    let title = fit_text(title, HAND_TITLE_FONT_SIZE, column_width);
+                    draw_text(
+                        &mut content,
+                        &title,
+                        HAND_TITLE_FONT_SIZE,
+                        left,
+                        page_height - top,
+                    );
+                    hand_top += HAND_TITLE_FONT_SIZE + TITLE_GAP;
+                }
+                document.draw_hand(
+                    &mut content,
+                    &hand.request,
+                    tile_set,
+                    layout,
+                    *scale,
+                    left,
+                    page_height - hand_top,
+                )?;
+            }
+            top += row_height + row_gap;
+            page_is_empty = false;
+        }
+        document.add_page(page_width, page_height, content);
+
+        Ok(document.write())
+    }
+}
+
+impl RenderRequest {
+    /// Checks the hand with [`check`](Self::check), and draws it on a PDF
+    /// page of the same size, with tiles `tile_width_mm` millimetres wide.
+    ///
+    /// The tiles are drawn as vector paths from `vector_tile_set`, which
+    /// must have the same tiles as `tile_set`, e.g. the one of a
+    /// [built-in tile set](crate::tile_sets::BuiltInTileSet::vector_loader).
+    /// Without it, the raster images of `tile_set` are embedded and scaled
+    /// to the print size.
+    pub fn render_pdf(
+        &self,
+        tile_set: &dyn RasterTileSet,
+        vector_tile_set: Option<&VectorTileSet>,
+        tile_width_mm: f64,
+    ) -> Result<RenderedImage, RenderError> {
+        let warnings = self.check(tile_set)?;
+        self.render_pdf_checked(tile_set, vector_tile_set, tile_width_mm, warnings)
+    }
+
+    /// Draws a hand already checked with [`check`](Self::check) like
+    /// [`render_pdf`](Self::render_pdf); `warnings` are the ones returned by
+    /// the check.
+    pub fn render_pdf_checked(
+        &self,
+        tile_set: &dyn RasterTileSet,
+        vector_tile_set: Option<&VectorTileSet>,
+        tile_width_mm: f64,
+        warnings: Vec<HandWarning>,
+    ) -> Result<RenderedImage, RenderError> {
+        let render_time = Instant::now();
+        let layout = self.layout(tile_set);
+        let tile_width = tile_set.tile_width() * self.style.scale.factor();
+        let scale = tile_width_mm * POINTS_PER_MM / f64::from(tile_width);
+        let page_width = (f64::from(layout.width) * scale).max(1.0);
+        let page_height = (f64::from(layout.height) * scale).max(1.0);
+        let mut document = PdfDocument::new(vector_tile_set);
+        let mut content = String::new();
+        document.draw_hand(
+            &mut content,
+            self,
+            tile_set,
+            &layout,
+            scale,
+            0.0,
+            page_height,
+        )?;
+        document.add_page(page_width, page_height, content);
+        let render_time = render_time.elapsed();
+
+        let encode_time = Instant::now();
+        let data = document.write();
+        let encode_time = encode_time.elapsed();
+
+        Ok(RenderedImage {
+            data,
+            warnings,
+            render_time,
+            encode_time,
+        })
+    }
+}
+
+struct PdfPage {
+    width: f64,
+    height: f64,
+    content: String,
+}
+
+/// A PDF document being built. The image or drawing of each tile is stored
+/// once, and shared by all the pages using it.
+struct PdfDocument<'a> {
+    vector_tile_set: Option<&'a VectorTileSet>,
+    images: Vec<RgbaImage>,
+    image_indices: HashMap<(TileStyle, String), usize>,
+    drawings: Vec<TileDrawing>,
+    drawing_indices: HashMap<(TileStyle, String), usize>,
+    pages: Vec<PdfPage>,
+}
+
+impl<'a> PdfDocument<'a> {
+    fn new(vector_tile_set: Option<&'a VectorTileSet>) -> Self {
+        Self {
+            vector_tile_set,
+            images: Vec::new(),
+            image_indices: HashMap::new(),
+            drawings: Vec::new(),
+            drawing_indices: HashMap::new(),
+            pages: Vec::new(),
+        }
+    }
+
+    fn add_page(&mut self, width: f64, height: f64, content: String) {
+        self.pages.push(PdfPage {
+            width,
+            height,
+            content,
+        });
+    }
+
+    /// Draws a hand with its top left corner at (`left`, `top`), in points
+    /// from the bottom left corner of the page, with `scale` points per pixel
+    /// of the rendered image.
+    #[expect(clippy::too_many_arguments)]
+    fn draw_hand(
+        &mut self,
+        content: &mut String,
+        request: &RenderRequest,
+        tile_set: &dyn RasterTileSet,
+        layout: &HandLayout,
+        scale: f64,
+        left: f64,
+        top: f64,
+    ) -> Result<(), RenderError> {
+        let styled_tile_set = request.styled_tile_set(tile_set);
+        for tile in &layout.tiles {
+            let (asset_tile, asset_name) = tile_asset(tile.tile);
+            let key = (request.style, asset_name);
+            let width = f64::from(tile.width) * scale;
+            let height = f64::from(tile.height) * scale;
+            let x = left + f64::from(tile.x) * scale;
+            let y = top - f64::from(tile.y) * scale - height;
+
+            if let Some(vector_tile_set) = self.vector_tile_set {
+                let index = match self.drawing_indices.get(&key) {
+                    Some(&index) => index,
+                    None => {
+                        self.drawings
+                            .push(vector_tile_set.draw(asset_tile, request.style));
+                        self.drawing_indices.insert(key, self.drawings.len() - 1);
+                        self.drawings.len() - 1
+                    }
+                };
+                let drawing = &self.drawings[index];
+                content.push_str(&format!(
+                    "q {} 0 0 {} {} {} cm /Fm{index} Do Q\n",
+                    format_decimal(width / f64::from(drawing.width)),
+                    format_decimal(height / f64::from(drawing.height)),
+                    format_decimal(x),
+                    format_decimal(y),
+                ));
+                continue;
+            }
+
+            let index = match self.image_indices.get(&key) {
+                Some(&index) => index,
+                None => {
+                    let image = styled_tile_set
+                        .tile_image(&asset_tile)
+                        .map_err(HandRenderError::from)?;
+                    self.images.push(image);
+                    self.image_indices.insert(key, self.images.len() - 1);
+                    self.images.len() - 1
+                }
+            };
+            content.push_str(&format!(
+                "q {} 0 0 {} {} {} cm /Im{index} Do Q\n",
+                format_decimal(width),
+                format_decimal(height),
+                format_decimal(x),
+                format_decimal(y),
+            ));
+        }
+
+        Ok(())
+    }
+
+    fn write(self) -> Vec<u8> {
+        const CATALOG_ID: usize = 1;
+        const PAGES_ID: usize = 2;
+        const FONT_ID: usize = 3;
+        const FIRST_IMAGE_ID: usize = 4;
+        // Every image is followed by its alpha channel.
+        let image_id = |index: usize| FIRST_IMAGE_ID + 2 * index;
+        let first_drawing_id = image_id(self.images.len());
+        let drawing_id = |index: usize| first_drawing_id + index;
+        let first_page_id = drawing_id(self.drawings.len());
+        // Every page is followed by its content stream.
+        let page_id = |index: usize| first_page_id + 2 * index;
+
+        let mut objects: Vec<Vec<u8>> = Vec::new();
+        let kids: Vec<_> = (0..self.pages.len())
+            .map(|index| format!("{} 0 R", page_id(index)))
+            .collect();
+        objects.push(format!("<< /Type /Catalog /Pages {PAGES_ID} 0 R >>").into_bytes());
+        objects.push(
+            format!(
+                "<< /Type /Pages /Kids [{}] /Count {} >>",
+                kids.join(" "),
+                self.pages.len()
+            )
+            .into_bytes(),
+        );
+        objects.push(
+            b"<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica /Encoding /WinAnsiEncoding >>"
+                .to_vec(),
+        );
+
+        for (index, image) in self.images.iter().enumerate() {
+            let (width, height) = image.dimensions();
+            let mut color = Vec::with_capacity(3 * image.as_raw().len() / 4);
+            let mut alpha = Vec::with_capacity(image.as_raw().len() / 4);
+            for pixel in image.pixels() {
+                color.extend_from_slice(&pixel.0[..3]);
+                alpha.push(pixel.0[3]);
+            }
+            objects.push(stream_object(
+                &format!(
+                    "/Type /XObject /Subtype /Image /Width {width} /Height {height} /ColorSpace \
+                     /DeviceRGB /BitsPerComponent 8 /SMask {} 0 R",
+                    image_id(index) + 1
+                ),
+                &color,
+            ));
+            objects.push(stream_object(
+                &format!(
+                    "/Type /XObject /Subtype /Image /Width {width} /Height {height} /ColorSpace \
+                     /DeviceGray /BitsPerComponent 8"
+                ),
+                &alpha,
+            ));
+        }
+
+        for drawing in &self.drawings {
+            let opacities: Vec<_> = drawing
+                .opacities
+                .iter()
+                .map(|&opacity| {
+                    let value = format_decimal(f64::from(opacity) / 1000.0);
+                    format!(
+                        "/{} << /ca {value} /CA {value} >>",
+                        opacity_state_name(opacity)
+                    )
+                })
+                .collect();
+            objects.push(stream_object(
+                &format!(
+                    "/Type /XObject /Subtype /Form /BBox [0 0 {} {}] /Resources << /ExtGState << \
+                     {} >> >>",
+                    format_decimal(drawing.width.into()),
+                    format_decimal(drawing.height.into()),
+                    opacities.join(" ")
+                ),
+                drawing.content.as_bytes(),
+            ));
+        }
+
+        let x_objects: Vec<_> = (0..self.images.len())
+            .map(|index| format!("/Im{index} {} 0 R", image_id(index)))
+            .chain(
+                (0..self.drawings.len())
+                    .map(|index| format!("/Fm{index} {} 0 R", drawing_id(index))),
+            )
+            .collect();
+        let resources = format!(
+            "<< /Font << /F1 {FONT_ID} 0 R >> /XObject << {} >> >>",
+            x_objects.join(" ")
+        );
+        for (index, page) in self.pages.iter().enumerate() {
+            objects.push(
+                format!(
+                    "<< /Type /Page /Parent {PAGES_ID} 0 R /MediaBox [0 0 {} {}] /Resources {resources} \
+                     /Contents {} 0 R >>",
+                    format_decimal(page.width),
+                    format_decimal(page.height),
+                    page_id(index) + 1
+                )
+                .into_bytes(),
+            );
+            objects.push(stream_object("", page.content.as_bytes()));
+        }
+
+        let mut pdf = b"%PDF-1.4\n%\xe2\xe3\xcf\xd3\n".to_vec();
+        let mut offsets = Vec::with_capacity(objects.len());
+        for (index, object) in objects.iter().enumerate() {
+            offsets.push(pdf.len());
+            pdf.extend_from_slice(format!("{} 0 obj\n", index + 1).as_bytes());
+            pdf.extend_from_slice(object);
+            pdf.extend_from_slice(b"\nendobj\n");
+        }
+        let xref_offset = pdf.len();
+        pdf.extend_from_slice(
+            format!("xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1).as_bytes(),
+        );
+        for offset in offsets {
+            pdf.extend_from_slice(format!("{offset:010} 00000 n \n").as_bytes());
+        }
+        pdf.extend_from_slice(
+            format!(
+                "trailer\n<< /Size {} /Root {CATALOG_ID} 0 R >>\nstartxref\n{xref_offset}\n%%EOF\n",
+                objects.len() + 1
+            )
+            .as_bytes(),
+        );
+        pdf
+    }
+}
+
+/// Returns a compressed stream object with the given extra dictionary
+/// entries.
+fn stream_object(dictionary: &str, data: &[u8]) -> Vec<u8> {
+    let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+    encoder
+        .write_all(data)
+        .expect("writing to a Vec never fails");
+    let data = encoder.finish().expect("writing to a Vec never fails");
+
+    let mut object = format!(
+        "<< {dictionary} /Filter /FlateDecode /Length {} >>\nstream\n",
+        data.len()
+    )
+    .into_bytes();
+    object.extend_from_slice(&data);
+    object.extend_from_slice(b"\nendstream");
+    object
+}
+
+/// Draws a line of text with its top at `top`, in points from the bottom of
+/// the page.
+fn draw_text(content: &mut String, text: &str, font_size: f64, left: f64, top: f64) {
+    content.push_str(&format!(
+        "BT /F1 {} Tf {} {} Td {} Tj ET\n",
+        format_decimal(font_size),
+        format_decimal(left),
+        format_decimal(top - font_size * FONT_ASCENT),
+        pdf_string(text)
+    ));
+}
+
+/// Characters of the WinAnsi encoding outside of Latin-1, at codes 0x80 to
+/// 0x9f; the unused codes are `None`.
+const WIN_ANSI_EXTRA_CHARS: [Option<char>; 32] = [
+    Some('€'),
+    None,
+    Some('‚'),
+    Some('ƒ'),
+    Some('„'),
+    Some('…'),
+    Some('†'),
+    Some('‡'),
+    Some('ˆ'),
+    Some('‰'),
+    Some('Š'),
+    Some('‹'),
+    Some('Œ'),
+    None,
+    Some('Ž'),
+    None,
+    None,
+    Some('‘'),
+    Some('’'),
+    Some('“'),
+    Some('”'),
+    Some('•'),
+    Some('–'),
+    Some('—'),
+    Some('˜'),
+    Some('™'),
+    Some('š'),
+    Some('›'),
+    Some('œ'),
+    None,
+    Some('ž'),
+    Some('Ÿ'),
+];
+
+/// Encodes text as a PDF string in the WinAnsi encoding; characters outside
+/// of it are replaced with `?`.
+fn pdf_string(text: &str) -> String {
+    let mut string = String::from("(");
+    for c in text.chars() {
+        let code = match c {
+            '(' | ')' | '\\' => {
+                string.push('\\');
+                string.push(c);
+                continue;
+            }
+            ' '..='~' => {
+                string.push(c);
+                continue;
+            }
+            '\u{a0}'..='\u{ff}' => Some(u32::from(c)),
+            _ => WIN_ANSI_EXTRA_CHARS
+                .iter()
+                .position(|&extra| extra == Some(c))
+                .map(|index| 0x80 + index as u32),
+        };
+        match code {
+            Some(code) => string.push_str(&format!("\\{code:03o}")),
+            None => string.push('?'),
+        }
+    }
+    string.push(')');
+    string
+}
+
+fn text_width(text: &str, font_size: f64) -> f64 {
+    let width: u32 = text
+        .chars()
+        .map(|c| {
+            let width = match c {
+                ' '..='~' => HELVETICA_WIDTHS[c as usize - ' ' as usize],
+                _ => DEFAULT_CHAR_WIDTH,
+            };
+            u32::from(width)
+        })
+        .sum();
+    f64::from(width) * font_size / 1000.0
+}
+
+/// Shortens text to fit in `max_width` points, with an ellipsis at the end.
+fn fit_text(text: &str, font_size: f64, max_width: f64) -> String {
+    if text_width(text, font_size) <= max_width {
+        return text.to_string();
+    }
+
+    let mut shortened: String = text.to_string();
+    while !shortened.is_empty() && text_width(&format!("{shortened}..."), font_size) > max_width {
+        shortened.pop();
+    }
+    format!("{}...", shortened.trim_end())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+
+    use flate2::read::ZlibDecoder;
+    use riichi_hand::parser::HandParser;
+
+    use super::*;
+    use crate::tile_sets::{BuiltInTileSet, built_in_tile_set};
+
+    fn request(hand: &str) -> RenderRequest {
+        RenderRequest::new(HandParser::parse(hand).expect("test hands are valid"))
+    }
+
+    fn yellow() -> &'static BuiltInTileSet {
+        built_in_tile_set("Yellow").expect("built-in tile set")
+    }
+
+    fn render_sheet(sheet: &PrintSheet, titles: &[&str]) -> Vec<u8> {
+        let hands: Vec<_> = titles
+            .iter()
+            .map(|title| SheetHand {
+                request: request("123m"),
+                title: Some(title.to_string()),
+            })
+            .collect();
+        let tile_set = yellow();
+        sheet
+            .render(
+                &hands,
+                tile_set.tile_set(),
+                tile_set.vector_loader().map(|load| load()),
+            )
+            .expect("test hands can be rendered")
+    }
+
+    fn find(data: &[u8], needle: &[u8], from: usize) -> Option<usize> {
+        data[from..]
+            .windows(needle.len())
+            .position(|window| window == needle)
+            .map(|position| from + position)
+    }
+
+    fn number_after(data: &[u8], prefix: &[u8]) -> usize {
+        let start = find(data, prefix, 0).expect("prefix present") + prefix.len();
+        let digits: String = data[start..]
+            .iter()
+            .map(|&byte| char::from(byte))
+            .take_while(char::is_ascii_digit)
+            .collect();
+        digits.parse().expect("number after prefix")
+    }
+
+    /// Returns the offsets of the objects from the cross-reference table.
+    fn xref_offsets(pdf: &[u8]) -> Vec<usize> {
+        let xref = number_after(pdf, b"startxref\n");
+        let table = String::from_utf8(pdf[xref..].to_vec()).expect("xref table is ASCII");
+        let mut lines = table.lines();
+        assert_eq!(lines.next(), Some("xref"));
+        let count: usize = lines
+            .next()
+            .and_then(|line| line.strip_prefix("0 "))
+            .and_then(|count| count.parse().ok())
+            .expect("xref subsection header");
+        assert_eq!(lines.next(), Some("0000000000 65535 f "));
+        lines
+            .take(count - 1)
+            .map(|line| {
+                assert!(line.ends_with(" 00000 n "), "{line}");
+                line[..10].parse().expect("object offset")
+            })
+            .collect()
+    }
+
+    fn object(pdf: &[u8], id: usize) -> &[u8] {
+        let start = xref_offsets(pdf)[id - 1];
+        let end = find(pdf, b"\nendobj", start).expect("object end");
+        &pdf[start..end]
+    }
+
+    fn page_objects(pdf: &[u8]) -> Vec<&[u8]> {
+        (1..=xref_offsets(pdf).len())
+            .map(|id| object(pdf, id))
+            .filter(|object| find(object, b"/Type /Page ", 0).is_some())
+            .collect()
+    }
+
+    fn media_box(page: &[u8]) -> Vec<f64> {
+        let start = find(page, b"/MediaBox [", 0).expect("media box") + b"/MediaBox [".len();
+        let end = find(page, b"]", start).expect("media box end");
+        String::from_utf8_lossy(&page[start..end])
+            .split(' ')
+            .map(|number| number.parse().expect("media box number"))
+            .collect()
+    }
+
+    fn page_content(pdf: &[u8], page: &[u8]) -> String {
+        let stream = object(pdf, number_after(page, b"/Contents "));
+        let start = find(stream, b"stream\n", 0).expect("stream start") + b"stream\n".len();
+        let end = find(stream, b"\nendstream", start).expect("stream end");
+        let mut content = String::new();
+        ZlibDecoder::new(&stream[start..end])
+            .read_to_string(&mut content)
+            .expect("content stream is compressed text");
+        content
+    }
+
+    /// Returns the position and text of every line of text on the pages.
+    fn page_texts(pdf: &[u8]) -> Vec<Vec<(f64, f64, String)>> {
+        page_objects(pdf)
+            .into_iter()
+            .map(|page| {
+                page_content(pdf, page)
+                    .lines()
+                    .filter_map(|line| {
+                        let line = line.strip_prefix("BT /F1 ")?.strip_suffix(" Tj ET")?;
+                        let mut parts = line.splitn(5, ' ');
+                        let _font_size = parts.next()?;
+                        assert_eq!(parts.next(), Some("Tf"));
+                        let x = parts.next()?.parse().ok()?;
+                        let (y, text) = parts.next().zip(parts.next())?;
+                        let text = text.strip_prefix("Td (")?.strip_suffix(')')?;
+                        Some((x, y.parse().ok()?, text.to_string()))
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn xref_offsets_point_at_objects() {
+        let titles: Vec<_> = (1..=30).map(|index| format!("Hand {index}")).collect();
+        let titles: Vec<_> = titles.iter().map(String::as_str).collect();
+        let pdf = render_sheet(&PrintSheet::default(), &titles);
+
+        let offsets = xref_offsets(&pdf);
+        assert!(offsets.len() > 3);
+        for (index, &offset) in offsets.iter().enumerate() {
+            let header = format!("{} 0 obj\n", index + 1);
+            assert!(
+                pdf[offset..].starts_with(header.as_bytes()),
+                "object {} at offset {offset}",
+                index + 1
+            );
+        }
+        assert_eq!(
+            number_after(&pdf, b"/Size "),
+            offsets.len() + 1,
+            "trailer size"
+        );
+    }
+
+    #[test]
+    fn special_characters_are_escaped() {
+        assert_eq!(pdf_string("Quiz (1) \\ 2"), "(Quiz \\(1\\) \\\\ 2)");
+    }
+
+    #[test]
+    fn characters_outside_win_ansi_are_replaced() {
+        assert_eq!(
+            pdf_string("Café – “€” 中"),
+            "(Caf\\351 \\226 \\223\\200\\224 ?)"
+        );
+    }
+
+    #[test]
+    fn titles_are_escaped_on_pages() {
+        let sheet = PrintSheet {
+            title: Some("Quiz (part 1)".to_string()),
+            ..PrintSheet::default()
+        };
+        let pdf = render_sheet(&sheet, &["Wait: 1\\4m"]);
+
+        let texts: Vec<_> = page_texts(&pdf)
+            .concat()
+            .into_iter()
+            .map(|(_, _, text)| text)
+            .collect();
+        assert_eq!(texts, ["Quiz \\(part 1\\)", "Wait: 1\\\\4m"]);
+    }
+
+    #[test]
+    fn rows_wrap_after_columns() {
+        let sheet = PrintSheet {
+            columns: 2,
+            ..PrintSheet::default()
+        };
+        let pdf = render_sheet(&sheet, &["A", "B", "C", "D", "E"]);
+
+        let pages = page_texts(&pdf);
+        assert_eq!(pages.len(), 1);
+        let [a, b, c, d, e] = pages[0].as_slice() else {
+            panic!("unexpected texts: {:?}", pages[0]);
+        };
+        assert_eq!(a.1, b.1, "first row");
+        assert!(a.0 < b.0, "columns from the left");
+        assert_eq!(c.1, d.1, "second row");
+        assert!(c.1 < a.1, "rows from the top");
+        assert!(e.1 < c.1, "third row");
+        assert_eq!((a.0, b.0), (c.0, d.0), "columns are aligned");
+        assert_eq!(e.0, a.0, "last row starts in the first column");
+    }
+
+    #[test]
+    fn hands_continue_on_new_pages() {
+        let titles: Vec<_> = (1..=40).map(|index| format!("Hand {index}")).collect();
+        let titles: Vec<_> = titles.iter().map(String::as_str).collect();
+        for (paper, hands_per_page, page_size) in [
+            (PaperSize::A4, 16, [595.276, 841.89]),
+            (PaperSize::Letter, 14, [612.0, 792.0]),
+        ] {
+            let sheet = PrintSheet {
+                paper,
+                tile_width_mm: 14.0,
+                ..PrintSheet::default()
+            };
+            let pdf = render_sheet(&sheet, &titles);
+
+            let pages = page_texts(&pdf);
+            let counts: Vec<_> = pages.iter().map(Vec::len).collect();
+            let mut expected = vec![hands_per_page; 40 / hands_per_page];
+            expected.push(40 % hands_per_page);
+            assert_eq!(counts, expected, "{paper:?}");
+            let texts: Vec<_> = pages
+                .concat()
+                .into_iter()
+                .map(|(_, _, text)| text)
+                .collect();
+            assert_eq!(texts, titles, "{paper:?}");
+            for page in page_objects(&pdf) {
+                assert_eq!(media_box(page), [0.0, 0.0, page_size[0], page_size[1]]);
+            }
+            let first_row_top = pages[0][0].1;
+            assert_eq!(
+                pages.last().expect("pages")[0].1,
+                first_row_top,
+                "new pages start at the top, {paper:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn hand_page_has_size_of_hand() {
+        let tile_set = yellow();
+        let rendered = request("123m")
+            .render_pdf(tile_set.tile_set(), None, 8.0)
+            .expect("test hands can be rendered");
+
+        let pages = page_objects(&rendered.data);
+        assert_eq!(pages.len(), 1);
+        let tile_width = 8.0 * POINTS_PER_MM;
+        let tile_height = tile_width * f64::from(tile_set.tile_set().tile_height())
+            / f64::from(tile_set.tile_set().tile_width());
+        let [left, bottom, width, height] = media_box(pages[0])[..] else {
+            panic!("media box has 4 numbers");
+        };
+        assert_eq!((left, bottom), (0.0, 0.0));
+        assert!((width - 3.0 * tile_width).abs() < 0.001, "width {width}");
+        assert!((height - tile_height).abs() < 0.001, "height {height}");
+    }
+
+    #[test]
+    fn sheets_over_pixel_limit_are_rejected() {
+        let tile_set = yellow();
+        let hands: Vec<_> = (0..3)
+            .map(|_| SheetHand {
+                request: request("123m"),
+                title: None,
+            })
+            .collect();
+        let hand_pixels = hands[0].request.layout(tile_set.tile_set()).pixel_count();
+        let sheet = |max_output_pixels| PrintSheet {
+            max_output_pixels,
+            ..PrintSheet::default()
+        };
+
+        assert!(
+            sheet(3 * hand_pixels)
+                .check_pixels(&hands, tile_set.tile_set())
+                .is_ok()
+        );
+        let error = sheet(3 * hand_pixels - 1)
+            .render(&hands, tile_set.tile_set(), None)
+            .unwrap_err();
+        assert!(
+            matches!(error, RenderError::SheetTooLarge { pixels, .. } if pixels == 3 * hand_pixels),
+            "{error}"
+        );
+    }
+
+    #[test]
+    fn vector_tiles_are_drawn_as_forms() {
+        let tile_set = yellow();
+        let vector = request("123m_?11?z")
+            .render_pdf(
+                tile_set.tile_set(),
+                tile_set.vector_loader().map(|load| load()),
+                8.0,
+            )
+            .expect("test hands can be rendered");
+        let raster = request("123m_?11?z")
+            .render_pdf(tile_set.tile_set(), None, 8.0)
+            .expect("test hands can be rendered");
+
+        let count = |pdf: &[u8], needle: &[u8]| {
+            pdf.windows(needle.len())
+                .filter(|window| *window == needle)
+                .count()
+        };
+        // One drawing or image for each of 1m, 2m, 3m, 1z and the back.
+        assert_eq!(count(&vector.data, b"/Subtype /Form"), 5);
+        assert_eq!(count(&vector.data, b"/Subtype /Image"), 0);
+        assert_eq!(count(&raster.data, b"/Subtype /Form"), 0);
+        // Every image is followed by its alpha channel.
+        assert_eq!(count(&raster.data, b"/Subtype /Image"), 10);
+    }
+}
@@ -0,0 +1,209 @@
+use std::io::Cursor;
+use std::time::{Duration, Instant};
+
+use chombo_gen_common::hand::HandWarning;
+use image::{DynamicImage, ImageFormat, RgbaImage};
+use riichi_hand::Hand;
+use riichi_hand::parser::HandParser;
+use riichi_hand::raster_renderer::{RasterRenderer, RenderOptions, TileSet as RasterTileSet};
+
+use crate::error::RenderError;
+use crate::layout::{HandLayout, layout_hand};
+use crate::lint::lint;
+use crate::scaled_tile_set::ScaledTileSet;
+use crate::tile_theme::{ThemedTileSet, TileStyle};
+
+/// Limits protecting the renderer from hands that are too expensive to
+/// render.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct RenderLimits {
+    /// Maximum length of a hand description, in bytes.
+    pub max_hand_len: usize,
+    /// Maximum number of pixels of a rendered image.
+    pub max_output_pixels: u64,
+}
+
+impl Default for RenderLimits {
+    fn default() -> Self {
+        Self {
+            max_hand_len: 100,
+            max_output_pixels: 24_000_000,
+        }
+    }
+}
+
+impl RenderLimits {
+    pub fn check_hand_len(&self, hand: &str) -> Result<(), RenderError> {
+        if hand.len() > self.max_hand_len {
+            return Err(RenderError::HandTooLong {
+                length: hand.len(),
+                max_length: self.max_hand_len,
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Parses a hand description, rejecting descriptions that are too long.
+    pub fn parse_hand(&self, hand: &str) -> Result<Hand, RenderError> {
+        self.check_hand_len(hand)?;
+        Ok(HandParser::parse(hand)?)
+    }
+}
+
+/// Image format of rendered hands.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum OutputFormat {
+    #[default]
+    Png,
+    /// Lossless WebP.
+    WebP,
+}
+
+impl OutputFormat {
+    pub const ALL: [Self; 2] = [Self::Png, Self::WebP];
+
+    /// Returns the format whose files have given extension, e.g. `webp`.
+    #[must_use]
+    pub fn from_extension(extension: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|format| format.extension() == extension)
+    }
+
+    #[must_use]
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Png => "png",
+            Self::WebP => "webp",
+        }
+    }
+
+    #[must_use]
+    pub fn mime_type(self) -> &'static str {
+        match self {
+            Self::Png => "image/png",
+            Self::WebP => "image/webp",
+        }
+    }
+
+    fn image_format(self) -> ImageFormat {
+        match self {
+            Self::Png => ImageFormat::Png,
+            Self::WebP => ImageFormat::WebP,
+        }
+    }
+}
+
+/// A hand to render, with the options to render it with.
+#[derive(Debug, Clone)]
+pub struct RenderRequest {
+    pub hand: Hand,
+    pub style: TileStyle,
+    pub format: OutputFormat,
+    /// Whether to reject hands that have lint warnings.
+    pub strict: bool,
+    pub limits: RenderLimits,
+}
+
+impl RenderRequest {
+    #[must_use]
+    pub fn new(hand: Hand) -> Self {
+        Self {
+            hand,
+            style: TileStyle::default(),
+            format: OutputFormat::default(),
+            strict: false,
+            limits: RenderLimits::default(),
+        }
+    }
+
+    /// Checks the hand without rendering it: rejects hands with warnings in
+    /// strict mode, and hands whose image would be too large. Returns the
+    /// warnings about the hand.
+    pub fn check(&self, tile_set: &dyn RasterTileSet) -> Result<Vec<HandWarning>, RenderError> {
+        let warnings = lint(&self.hand);
+        if self.strict && !warnings.is_empty() {
+            return Err(RenderError::InvalidHand(warnings));
+        }
+
+        let layout = self.layout(tile_set);
+        if layout.pixel_count() > self.limits.max_output_pixels {
+            return Err(RenderError::ImageTooLarge {
+                width: layout.width,
+                height: layout.height,
+                max_pixels: self.limits.max_output_pixels,
+            });
+        }
+
+        Ok(warnings)
+    }
+
+    /// Returns the positions of the tiles in the rendered image.
+    #[must_use]
+    pub fn layout(&self, tile_set: &dyn RasterTileSet) -> HandLayout {
+        layout_hand(
+            &self.hand,
+            tile_set.tile_width() * self.style.scale.factor(),
+            tile_set.tile_height() * self.style.scale.factor(),
+            RenderOptions::default(),
+        )
+    }
+
+    /// Returns the tile set drawing the tiles in the requested style.
+    pub(crate) fn styled_tile_set<'a>(
+        &self,
+        tile_set: &'a dyn RasterTileSet,
+    ) -> ScaledTileSet<ThemedTileSet<&'a dyn RasterTileSet>> {
+        ScaledTileSet::new(ThemedTileSet::new(tile_set, self.style), self.style.scale)
+    }
+
+    /// Checks the hand with [`check`](Self::check), renders it and encodes
+    /// the image.
+    pub fn render(&self, tile_set: &dyn RasterTileSet) -> Result<RenderedImage, RenderError> {
+        let warnings = self.check(tile_set)?;
+        self.render_checked(tile_set, warnings)
+    }
+
+    /// Renders a hand already checked with [`check`](Self::check), and
+    /// encodes the image; `warnings` are the ones returned by the check.
+    pub fn render_checked(
+        &self,
+        tile_set: &dyn RasterTileSet,
+        warnings: Vec<HandWarning>,
+    ) -> Result<RenderedImage, RenderError> {
+        let render_time = Instant::now();
+        let image = RasterRenderer::render(
+            &self.hand,
+            &self.styled_tile_set(tile_set),
+            RenderOptions::default(),
+        )?;
+        let render_time = render_time.elapsed();
+
+        let encode_time = Instant::now();
+        let data = encode_image(image, self.format)?;
+        let encode_time = encode_time.elapsed();
+
+        Ok(RenderedImage {
+            data,
+            warnings,
+            render_time,
+            encode_time,
+        })
+    }
+}
+
+pub(crate) fn encode_image(image: RgbaImage, format: OutputFormat) -> Result<Vec<u8>, RenderError> {
+    let mut data = Vec::new();
+    DynamicImage::ImageRgba8(image).write_to(&mut Cursor::new(&mut data), format.image_format())?;
+    Ok(data)
+}
+
+#[derive(Debug, Clone)]
+pub struct RenderedImage {
+    /// The encoded image.
+    pub data: Vec<u8>,
+    pub warnings: Vec<HandWarning>,
+    pub render_time: Duration,
+    pub encode_time: Duration,
+}
@@ -0,0 +1,87 @@
+use image::imageops;
+use image::imageops::FilterType;
+use riichi_hand::HandTile;
+use riichi_hand::raster_renderer::{TileImageResult, TileSet as RasterTileSet};
+use schemars::JsonSchema;
+use serde::{Deserialize, Deserializer};
+
+const MAX_SCALE: u32 = 4;
+
+/// Integer factor the tile images are enlarged by, e.g. for high-DPI screens.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, JsonSchema)]
+#[schemars(with = "u32")]
+pub struct Scale(u32);
+
+impl Scale {
+    #[must_use]
+    pub fn factor(self) -> u32 {
+        self.0
+    }
+}
+
+impl Default for Scale {
+    fn default() -> Self {
+        Self(1)
+    }
+}
+
+impl<'de> Deserialize<'de> for Scale {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        // Query parameters in flattened structs are always passed as strings
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum ScaleValue {
+            Number(u32),
+            Text(String),
+        }
+
+        let scale = match ScaleValue::deserialize(deserializer)? {
+            ScaleValue::Number(scale) => Some(scale),
+            ScaleValue::Text(scale) => scale.parse().ok(),
+        };
+        match scale {
+            Some(scale @ 1..=MAX_SCALE) => Ok(Self(scale)),
+            _ => Err(serde::de::Error::custom(format!(
+                "scale must be an integer between 1 and {MAX_SCALE}"
+            ))),
+        }
+    }
+}
+
+/// A tile set enlarging the images of another tile set.
+#[derive(Debug)]
+pub struct ScaledTileSet<T> {
+    tile_set: T,
+    scale: Scale,
+}
+
+impl<T> ScaledTileSet<T> {
+    #[must_use]
+    pub fn new(tile_set: T, scale: Scale) -> Self {
+        Self { tile_set, scale }
+    }
+}
+
+impl<T: RasterTileSet> RasterTileSet for ScaledTileSet<T> {
+    fn tile_image(&self, hand_tile: &HandTile) -> TileImageResult {
+        let image = self.tile_set.tile_image(hand_tile)?;
+        if self.scale == Scale::default() {
+            return Ok(image);
+        }
+
+        Ok(imageops::resize(
+            &image,
+            image.width() * self.scale.factor(),
+            image.height() * self.scale.factor(),
+            FilterType::CatmullRom,
+        ))
+    }
+
+    fn tile_width(&self) -> u32 {
+        self.tile_set.tile_width() * self.scale.factor()
+    }
+
+    fn tile_height(&self) -> u32 {
+        self.tile_set.tile_height() * self.scale.factor()
+    }
+}
@@ -0,0 +1,137 @@
+use riichi_hand::raster_renderer::TileSet as RasterTileSet;
+use riichi_hand::raster_renderer::fluffy_stuff_tile_sets::{
+    BLACK_FLUFFY_STUFF_TILE_SET, RED_FLUFFY_STUFF_TILE_SET, YELLOW_FLUFFY_STUFF_TILE_SET,
+};
+use riichi_hand::raster_renderer::martin_persson_tile_sets::MARTIN_PERSSON_TILE_SET;
+use riichi_hand::{HandTile, Suite, Tile, TilePlacement};
+
+use crate::vector_tile_set::{
+    BLACK_FLUFFY_STUFF_VECTOR_TILE_SET, RED_FLUFFY_STUFF_VECTOR_TILE_SET, VectorTileSet,
+    YELLOW_FLUFFY_STUFF_VECTOR_TILE_SET,
+};
+
+const FLUFFY_STUFF_ATTRIBUTION: &str = "riichi-mahjong-tiles by FluffyStuff";
+const FLUFFY_STUFF_ATTRIBUTION_URL: &str = "https://github.com/FluffyStuff/riichi-mahjong-tiles";
+const FLUFFY_STUFF_LICENSE: &str = "CC0-1.0";
+const MARTIN_PERSSON_ATTRIBUTION: &str = "mahjong tiles by Martin Persson";
+const MARTIN_PERSSON_ATTRIBUTION_URL: &str = "https://www.martinpersson.org/";
+const MARTIN_PERSSON_LICENSE: &str =
+    "Free for personal and commercial use with a link to the author's page";
+
+/// A tile set bundled with the library.
+#[derive(Debug, Clone, Copy)]
+pub struct BuiltInTileSet {
+    pub id: &'static str,
+    pub name: &'static str,
+    pub attribution: &'static str,
+    pub attribution_url: &'static str,
+    pub license: &'static str,
+    tile_set: fn() -> &'static (dyn RasterTileSet + Sync),
+    vector_tile_set: Option<fn() -> &'static VectorTileSet>,
+}
+
+impl BuiltInTileSet {
+    const fn fluffy_stuff(
+        id: &'static str,
+        name: &'static str,
+        tile_set: fn() -> &'static (dyn RasterTileSet + Sync),
+        vector_tile_set: fn() -> &'static VectorTileSet,
+    ) -> Self {
+        Self {
+            id,
+            name,
+            attribution: FLUFFY_STUFF_ATTRIBUTION,
+            attribution_url: FLUFFY_STUFF_ATTRIBUTION_URL,
+            license: FLUFFY_STUFF_LICENSE,
+            tile_set,
+            vector_tile_set: Some(vector_tile_set),
+        }
+    }
+
+    /// Returns the tile images, loading them on first use.
+    #[must_use]
+    pub fn tile_set(&self) -> &'static (dyn RasterTileSet + Sync) {
+        (self.tile_set)()
+    }
+
+    /// Returns the function loading the tile images, for callers that want
+    /// to defer loading them.
+    #[must_use]
+    pub fn loader(&self) -> fn() -> &'static (dyn RasterTileSet + Sync) {
+        self.tile_set
+    }
+
+    /// Returns the function loading the vector tile images drawn in PDF
+    /// documents, if the tile set has them.
+    #[must_use]
+    pub fn vector_loader(&self) -> Option<fn() -> &'static VectorTileSet> {
+        self.vector_tile_set
+    }
+}
+
+/// All built-in tile sets, in the order they are presented to the users.
+pub static BUILT_IN_TILE_SETS: [BuiltInTileSet; 4] = [
+    BuiltInTileSet::fluffy_stuff(
+        "Yellow",
+        "Yellow",
+        || &*YELLOW_FLUFFY_STUFF_TILE_SET,
+        || &*YELLOW_FLUFFY_STUFF_VECTOR_TILE_SET,
+    ),
+    BuiltInTileSet::fluffy_stuff(
+        "Red",
+        "Red",
+        || &*RED_FLUFFY_STUFF_TILE_SET,
+        || &*RED_FLUFFY_STUFF_VECTOR_TILE_SET,
+    ),
+    BuiltInTileSet::fluffy_stuff(
+        "Black",
+        "Black",
+        || &*BLACK_FLUFFY_STUFF_TILE_SET,
+        || &*BLACK_FLUFFY_STUFF_VECTOR_TILE_SET,
+    ),
+    BuiltInTileSet {
+        id: "MartinPersson",
+        name: "Martin Persson",
+        attribution: MARTIN_PERSSON_ATTRIBUTION,
+        attribution_url: MARTIN_PERSSON_ATTRIBUTION_URL,
+        license: MARTIN_PERSSON_LICENSE,
+        tile_set: || &*MARTIN_PERSSON_TILE_SET,
+        vector_tile_set: None,
+    },
+];
+
+#[must_use]
+pub fn built_in_tile_set(id: &str) -> Option<&'static BuiltInTileSet> {
+    BUILT_IN_TILE_SETS.iter().find(|tile_set| tile_set.id == id)
+}
+
+/// Returns the name of the image file (without extension) containing given
+/// tile, following the naming of FluffyStuff's tile images.
+pub fn tile_file_name(tile: &Tile) -> String {
+    let value = tile.value.0;
+    match tile.suite {
+        Suite::Manzu if value == 0 => "Man5-Dora".to_string(),
+        Suite::Pinzu if value == 0 => "Pin5-Dora".to_string(),
+        Suite::Souzu if value == 0 => "Sou5-Dora".to_string(),
+        Suite::Manzu => format!("Man{value}"),
+        Suite::Pinzu => format!("Pin{value}"),
+        Suite::Souzu => format!("Sou{value}"),
+        Suite::Honor => ["Ton", "Nan", "Shaa", "Pei", "Haku", "Hatsu", "Chun"]
+            [usize::from(value) - 1]
+            .to_string(),
+        Suite::Any => "Back".to_string(),
+    }
+}
+
+/// Returns the tile whose image is used to draw `tile` in exported documents,
+/// and the name of the image. Shifted tiles use the same image as rotated
+/// ones.
+pub(crate) fn tile_asset(tile: HandTile) -> (HandTile, String) {
+    match tile.placement {
+        TilePlacement::Normal => (tile, tile_file_name(&tile.tile)),
+        TilePlacement::Rotated | TilePlacement::RotatedAndShifted => (
+            HandTile::new(tile.tile, TilePlacement::Rotated),
+            format!("{}-Rotated", tile_file_name(&tile.tile)),
+        ),
+    }
+}
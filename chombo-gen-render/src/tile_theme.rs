@@ -0,0 +1,322 @@
+use chombo_gen_common::theme::{Color, TileTheme};
+use image::{Rgba, RgbaImage};
+use riichi_hand::raster_renderer::{TileImageResult, TileSet as RasterTileSet};
+use riichi_hand::{HandTile, Suite};
+use schemars::JsonSchema;
+use serde::de::value::{BorrowedStrDeserializer, Error as ValueError, MapDeserializer};
+use serde::de::{IntoDeserializer, Visitor};
+use serde::{Deserialize, Deserializer, forward_to_deserialize_any};
+
+use crate::scaled_tile_set::Scale;
+
+/// Pixels less saturated than this are left untouched when recolouring the
+/// tile faces (white background, black characters, anti-aliased edges).
+const MIN_INK_SATURATION: f32 = 0.2;
+/// Saturation above which a pixel is entirely replaced by the theme colour.
+const FULL_INK_SATURATION: f32 = 0.5;
+const MIN_INK_VALUE: f32 = 0.08;
+/// Pixels of the tile back brighter and less saturated than this are
+/// considered to be the tile edge, not the back.
+const MIN_BACK_SATURATION: f32 = 0.25;
+const MAX_BACK_EDGE_VALUE: f32 = 0.5;
+
+/// Options changing how the tiles of any tile set are drawn.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Deserialize, JsonSchema)]
+pub struct TileStyle {
+    #[serde(default)]
+    pub theme: TileTheme,
+    /// Colour of the back of face-down tiles.
+    #[serde(default)]
+    pub back_color: Option<Color>,
+    #[serde(default)]
+    pub scale: Scale,
+}
+
+impl TileStyle {
+    /// Parses the style from `(name, value)` pairs, e.g. `("scale", "2")`,
+    /// the same way as the query parameters of the API.
+    pub fn from_params<'a>(
+        params: impl IntoIterator<Item = (&'a str, &'a str)>,
+    ) -> Result<Self, String> {
+        let params = params
+            .into_iter()
+            .map(|(name, value)| (name, ParamValue(value)));
+        Self::deserialize(MapDeserializer::<_, ValueError>::new(params))
+            .map_err(|error| error.to_string())
+    }
+
+    /// Parses the style from the options that are set, e.g. on the command
+    /// line, with [`from_params`](Self::from_params).
+    pub fn from_options(
+        theme: Option<&str>,
+        back_color: Option<&str>,
+        scale: Option<&str>,
+    ) -> Result<Self, String> {
+        let params = [
+            ("theme", theme),
+            ("back_color", back_color),
+            ("scale", scale),
+        ]
+        .into_iter()
+        .filter_map(|(name, value)| value.map(|value| (name, value)));
+        Self::from_params(params)
+    }
+}
+
+/// Value of a parameter in [`TileStyle::from_params`]; unlike a plain
+/// string, it can be deserialized as an `Option` that is set, like query
+/// parameters.
+struct ParamValue<'a>(&'a str);
+
+impl<'de> IntoDeserializer<'de, ValueError> for ParamValue<'de> {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self {
+        self
+    }
+}
+
+impl<'de> Deserializer<'de> for ParamValue<'de> {
+    type Error = ValueError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, ValueError> {
+        visitor.visit_borrowed_str(self.0)
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, ValueError> {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, ValueError> {
+        BorrowedStrDeserializer::new(self.0).deserialize_enum(name, variants, visitor)
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf unit unit_struct newtype_struct seq tuple
+        tuple_struct map struct identifier ignored_any
+    }
+}
+
+/// Colours replacing the red, green, and blue "ink" of the tile faces.
+#[derive(Debug, Clone, Copy)]
+struct InkPalette {
+    red: Color,
+    green: Color,
+    blue: Color,
+}
+
+const HIGH_CONTRAST_PALETTE: InkPalette = InkPalette {
+    red: Color::new(224, 0, 0),
+    green: Color::new(0, 140, 0),
+    blue: Color::new(0, 64, 255),
+};
+
+/// Vermillion, bluish green and blue from the Okabe-Ito palette.
+const COLORBLIND_PALETTE: InkPalette = InkPalette {
+    red: Color::new(213, 94, 0),
+    green: Color::new(0, 158, 115),
+    blue: Color::new(0, 114, 178),
+};
+
+fn ink_palette(theme: TileTheme) -> Option<InkPalette> {
+    match theme {
+        TileTheme::Default => None,
+        TileTheme::HighContrast => Some(HIGH_CONTRAST_PALETTE),
+        TileTheme::Colorblind => Some(COLORBLIND_PALETTE),
+    }
+}
+
+/// A tile set recolouring the images of another tile set according to a
+/// [`TileStyle`].
+#[derive(Debug)]
+pub struct ThemedTileSet<T> {
+    tile_set: T,
+    style: TileStyle,
+}
+
+impl<T> ThemedTileSet<T> {
+    #[must_use]
+    pub fn new(tile_set: T, style: TileStyle) -> Self {
+        Self { tile_set, style }
+    }
+}
+
+impl<T: RasterTileSet> RasterTileSet for ThemedTileSet<T> {
+    fn tile_image(&self, hand_tile: &HandTile) -> TileImageResult {
+        let mut image = self.tile_set.tile_image(hand_tile)?;
+
+        if hand_tile.tile.suite == Suite::Any {
+            if let Some(back_color) = self.style.back_color {
+                recolor_back(&mut image, back_color);
+            }
+        } else if let Some(palette) = ink_palette(self.style.theme) {
+            recolor_ink(&mut image, palette);
+        }
+
+        Ok(image)
+    }
+
+    fn tile_width(&self) -> u32 {
+        self.tile_set.tile_width()
+    }
+
+    fn tile_height(&self) -> u32 {
+        self.tile_set.tile_height()
+    }
+}
+
+fn recolor_ink(image: &mut RgbaImage, palette: InkPalette) {
+    for pixel in image.pixels_mut() {
+        let [red, green, blue, alpha] = pixel.0;
+        let [red, green, blue] = ink_color([red, green, blue], palette);
+        *pixel = Rgba([red, green, blue, alpha]);
+    }
+}
+
+/// Returns the colour of the tile faces drawn as `color` in the theme, e.g.
+/// for the shapes of vector tile images.
+pub(crate) fn themed_ink_color(color: [u8; 3], theme: TileTheme) -> [u8; 3] {
+    match ink_palette(theme) {
+        Some(palette) => ink_color(color, palette),
+        None => color,
+    }
+}
+
+fn ink_color(color: [u8; 3], palette: InkPalette) -> [u8; 3] {
+    let (hue, saturation, value) = rgb_to_hsv(color);
+    if saturation < MIN_INK_SATURATION || value < MIN_INK_VALUE {
+        return color;
+    }
+
+    let target = match hue {
+        0.0..20.0 | 340.0..=360.0 => palette.red,
+        75.0..170.0 => palette.green,
+        190.0..280.0 => palette.blue,
+        _ => return color,
+    };
+    let weight =
+        ((saturation - MIN_INK_SATURATION) / (FULL_INK_SATURATION - MIN_INK_SATURATION)).min(1.0);
+    let [red, green, blue] = color;
+    let mix = |from: u8, to: u8| {
+        (f32::from(from) + (f32::from(to) - f32::from(from)) * weight).round() as u8
+    };
+    [
+        mix(red, target.red),
+        mix(green, target.green),
+        mix(blue, target.blue),
+    ]
+}
+
+/// Replaces the colour of the tile back, keeping its shading.
+fn recolor_back(image: &mut RgbaImage, color: Color) {
+    let back_value = |pixel: &Rgba<u8>| {
+        let [red, green, blue, alpha] = pixel.0;
+        back_value([red, green, blue]).filter(|_| alpha > 0)
+    };
+    let mut values: Vec<f32> = image.pixels().filter_map(back_value).collect();
+    if values.is_empty() {
+        return;
+    }
+    // Most of the back has a single colour, so the median is used as the
+    // reference brightness that the target colour is mapped to.
+    let median_index = values.len() / 2;
+    let (_, &mut median_value, _) = values.select_nth_unstable_by(median_index, f32::total_cmp);
+    if median_value == 0.0 {
+        return;
+    }
+
+    for pixel in image.pixels_mut() {
+        if let Some(value) = back_value(pixel) {
+            let [red, green, blue] = back_color(value, median_value, color);
+            *pixel = Rgba([red, green, blue, pixel.0[3]]);
+        }
+    }
+}
+
+/// Returns the value (brightness) of `color` if it is the colour of the tile
+/// back, and not of its edge.
+pub(crate) fn back_value(color: [u8; 3]) -> Option<f32> {
+    let (_, saturation, value) = rgb_to_hsv(color);
+    (saturation >= MIN_BACK_SATURATION || value <= MAX_BACK_EDGE_VALUE).then_some(value)
+}
+
+/// Returns the colour replacing a colour of the tile back with given `value`,
+/// so that the non-zero `reference_value` is mapped to `color`.
+pub(crate) fn back_color(value: f32, reference_value: f32, color: Color) -> [u8; 3] {
+    let (target_hue, target_saturation, target_value) =
+        rgb_to_hsv([color.red, color.green, color.blue]);
+    let value = (value / reference_value * target_value).min(1.0);
+    hsv_to_rgb(target_hue, target_saturation, value)
+}
+
+/// Returns hue (in degrees), saturation and value of a colour.
+fn rgb_to_hsv(color: [u8; 3]) -> (f32, f32, f32) {
+    let [red, green, blue] = color.map(|component| f32::from(component) / 255.0);
+    let max = red.max(green).max(blue);
+    let min = red.min(green).min(blue);
+    let delta = max - min;
+
+    let hue = if delta == 0.0 {
+        0.0
+    } else if max == red {
+        60.0 * ((green - blue) / delta).rem_euclid(6.0)
+    } else if max == green {
+        60.0 * ((blue - red) / delta + 2.0)
+    } else {
+        60.0 * ((red - green) / delta + 4.0)
+    };
+    let saturation = if max == 0.0 { 0.0 } else { delta / max };
+
+    (hue, saturation, max)
+}
+
+fn hsv_to_rgb(hue: f32, saturation: f32, value: f32) -> [u8; 3] {
+    let chroma = value * saturation;
+    let x = chroma * (1.0 - ((hue / 60.0).rem_euclid(2.0) - 1.0).abs());
+    let m = value - chroma;
+    let (red, green, blue) = match hue {
+        0.0..60.0 => (chroma, x, 0.0),
+        60.0..120.0 => (x, chroma, 0.0),
+        120.0..180.0 => (0.0, chroma, x),
+        180.0..240.0 => (0.0, x, chroma),
+        240.0..300.0 => (x, 0.0, chroma),
+        _ => (chroma, 0.0, x),
+    };
+
+    [red, green, blue].map(|component| ((component + m) * 255.0).round().clamp(0.0, 255.0) as u8)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn options_are_parsed_like_query_parameters() {
+        let style = TileStyle::from_options(Some("colorblind"), Some("#3060c0"), Some("2"))
+            .expect("valid options");
+
+        assert_eq!(style.theme, TileTheme::Colorblind);
+        assert_eq!(style.back_color, Some(Color::new(0x30, 0x60, 0xc0)));
+        assert_eq!(style.scale.factor(), 2);
+    }
+
+    #[test]
+    fn missing_options_have_default_values() {
+        let style = TileStyle::from_options(None, None, None).expect("valid options");
+
+        assert_eq!(style, TileStyle::default());
+    }
+
+    #[test]
+    fn invalid_back_color_is_rejected() {
+        let error = TileStyle::from_options(None, Some("blue"), None).unwrap_err();
+
+        assert!(error.contains("#rrggbb"), "{error}");
+    }
+}
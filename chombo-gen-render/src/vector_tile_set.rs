@@ -0,0 +1,398 @@
+//! Vector images of the FluffyStuff tiles, drawn in PDF documents instead of
+//! the raster images of their tile sets.
+
+use std::collections::{BTreeSet, HashMap};
+use std::fmt::{Debug, Formatter};
+use std::sync::LazyLock;
+
+use chombo_gen_common::theme::Color;
+use riichi_hand::{HandTile, Suite, TilePlacement};
+use usvg::tiny_skia_path::{Path as PathData, PathSegment, Point};
+use usvg::{FillRule, Group, LineCap, LineJoin, Node, Paint, Path, Transform, Tree};
+
+use crate::latex::format_decimal;
+use crate::tile_sets::tile_file_name;
+use crate::tile_theme::{TileStyle, back_color, back_value, themed_ink_color};
+
+/// Margin around the tile faces, relative to the tile size, the same as in
+/// the raster FluffyStuff tile sets.
+const FACE_MARGIN: f32 = 0.05;
+
+macro_rules! fluffy_stuff_svgs {
+    ($color:literal) => {
+        fluffy_stuff_svgs!(
+            $color;
+            "Front", "Back",
+            "Man1", "Man2", "Man3", "Man4", "Man5", "Man5-Dora", "Man6", "Man7", "Man8", "Man9",
+            "Pin1", "Pin2", "Pin3", "Pin4", "Pin5", "Pin5-Dora", "Pin6", "Pin7", "Pin8", "Pin9",
+            "Sou1", "Sou2", "Sou3", "Sou4", "Sou5", "Sou5-Dora", "Sou6", "Sou7", "Sou8", "Sou9",
+            "Ton", "Nan", "Shaa", "Pei", "Haku", "Hatsu", "Chun"
+        )
+    };
+    ($color:literal; $($name:literal),+) => {
+        [$((
+            $name,
+            include_bytes!(concat!(
+                env!("CARGO_MANIFEST_DIR"),
+                "/tilesets/FluffyStuff/",
+                $color,
+                "/",
+                $name,
+                ".svg"
+            ))
+            .as_slice(),
+        )),+]
+    };
+}
+
+pub static YELLOW_FLUFFY_STUFF_VECTOR_TILE_SET: LazyLock<VectorTileSet> =
+    LazyLock::new(|| VectorTileSet::new(&fluffy_stuff_svgs!("Yellow")));
+pub static RED_FLUFFY_STUFF_VECTOR_TILE_SET: LazyLock<VectorTileSet> =
+    LazyLock::new(|| VectorTileSet::new(&fluffy_stuff_svgs!("Red")));
+pub static BLACK_FLUFFY_STUFF_VECTOR_TILE_SET: LazyLock<VectorTileSet> =
+    LazyLock::new(|| VectorTileSet::new(&fluffy_stuff_svgs!("Black")));
+
+/// Tile images made of a front and back background, and a face drawn over
+/// the front, like the raster FluffyStuff tile sets.
+///
+/// Only solid colours are drawn; filters, such as the blur of the tile
+/// edges, are left out.
+pub struct VectorTileSet {
+    front: Tree,
+    back: Tree,
+    faces: HashMap<String, Tree>,
+    /// Brightness of the main colour of the back, mapped to the back colour
+    /// of [`TileStyle`].
+    back_value: Option<f32>,
+}
+
+impl VectorTileSet {
+    /// Parses the SVG images named after [`tile_file_name`], plus `Front`
+    /// and `Back`.
+    fn new(svgs: &[(&str, &[u8])]) -> Self {
+        let options = usvg::Options::default();
+        let mut trees: HashMap<_, _> = svgs
+            .iter()
+            .map(|(name, data)| {
+                let tree = Tree::from_data(data, &options).expect("bundled tile images are valid");
+                (name.to_string(), tree)
+            })
+            .collect();
+        let front = trees.remove("Front").expect("tile front is bundled");
+        let back = trees.remove("Back").expect("tile back is bundled");
+        let back_value = largest_back_fill(back.root(), None).map(|(value, _)| value);
+
+        Self {
+            front,
+            back,
+            faces: trees,
+            back_value,
+        }
+    }
+
+    /// Draws a tile in a box of the size of its raster image, in pixels,
+    /// recoloured like [`ThemedTileSet`](crate::tile_theme::ThemedTileSet)
+    /// does.
+    pub(crate) fn draw(&self, tile: HandTile, style: TileStyle) -> TileDrawing {
+        let size = self.front.size();
+        let (width, height) = (size.width(), size.height());
+        let face_margin = Transform::from_scale(1.0 - 2.0 * FACE_MARGIN, 1.0 - 2.0 * FACE_MARGIN)
+            .post_translate(width * FACE_MARGIN, height * FACE_MARGIN);
+        let (box_width, box_height, background_transform, face_transform) = match tile.placement {
+            TilePlacement::Normal => (width, height, Transform::identity(), face_margin),
+            // The background is mirrored along its diagonal, so that the
+            // light seems to come from the same side as on upright tiles,
+            // and the face is rotated clockwise.
+            TilePlacement::Rotated | TilePlacement::RotatedAndShifted => (
+                height,
+                width,
+                Transform::from_row(0.0, 1.0, 1.0, 0.0, 0.0, 0.0),
+                Transform::from_row(0.0, 1.0, -1.0, 0.0, height, 0.0).pre_concat(face_margin),
+            ),
+        };
+
+        let mut drawing = TileDrawing {
+            width: box_width,
+            height: box_height,
+            // SVG coordinates grow downwards, unlike PDF ones.
+            content: format!("1 0 0 -1 0 {} cm\n", format_decimal(box_height.into())),
+            opacities: BTreeSet::new(),
+        };
+        if tile.tile.suite == Suite::Any {
+            let recolor = |color| match (style.back_color, self.back_value) {
+                (Some(target), Some(reference_value)) => {
+                    recolor_back_fill(color, reference_value, target)
+                }
+                _ => color,
+            };
+            drawing.draw_group(self.back.root(), background_transform, 1.0, &recolor);
+        } else {
+            let recolor = |color| themed_ink_color(color, style.theme);
+            drawing.draw_group(self.front.root(), background_transform, 1.0, &recolor);
+            if let Some(face) = self.faces.get(&tile_file_name(&tile.tile)) {
+                drawing.draw_group(face.root(), face_transform, 1.0, &recolor);
+            }
+        }
+
+        drawing
+    }
+}
+
+impl Debug for VectorTileSet {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("VectorTileSet")
+            .field("faces", &self.faces.len())
+            .finish_non_exhaustive()
+    }
+}
+
+fn recolor_back_fill(color: [u8; 3], reference_value: f32, target: Color) -> [u8; 3] {
+    match back_value(color) {
+        Some(value) => back_color(value, reference_value, target),
+        None => color,
+    }
+}
+
+/// Returns the brightness and area of the largest shape filled with the
+/// colour of the tile back.
+fn largest_back_fill(group: &Group, mut largest: Option<(f32, f32)>) -> Option<(f32, f32)> {
+    for child in group.children() {
+        match child {
+            Node::Group(group) => largest = largest_back_fill(group, largest),
+            Node::Path(path) => {
+                let Some(Paint::Color(color)) = path.fill().map(|fill| fill.paint()) else {
+                    continue;
+                };
+                let Some(value) = back_value([color.red, color.green, color.blue]) else {
+                    continue;
+                };
+                let bounding_box = path.bounding_box();
+                let area = bounding_box.width() * bounding_box.height();
+                if value > 0.0 && largest.is_none_or(|(_, largest_area)| area > largest_area) {
+                    largest = Some((value, area));
+                }
+            }
+            _ => {}
+        }
+    }
+    largest
+}
+
+/// A tile drawn with PDF operators in a box of `width` × `height` units, to
+/// be used as a form XObject.
+#[derive(Debug)]
+pub(crate) struct TileDrawing {
+    pub(crate) width: f32,
+    pub(crate) height: f32,
+    pub(crate) content: String,
+    /// Opacities used in the drawing, in thousandths; each is set with the
+    /// graphics state named by [`opacity_state_name`].
+    pub(crate) opacities: BTreeSet<u16>,
+}
+
+impl TileDrawing {
+    fn draw_group(
+        &mut self,
+        group: &Group,
+        transform: Transform,
+        opacity: f32,
+        recolor: &dyn Fn([u8; 3]) -> [u8; 3],
+    ) {
+        let opacity = opacity * group.opacity().get();
+        let clip_path = group.clip_path();
+        if let Some(clip_path) = clip_path {
+            // Clip paths aren't affected by the transforms of the groups
+            // using them, so their shapes are transformed before clipping.
+            self.content.push_str("q\n");
+            let transform = transform
+                .pre_concat(group.abs_transform())
+                .pre_concat(clip_path.transform());
+            self.add_clip(clip_path.root(), transform);
+            self.content.push_str("W n\n");
+        }
+
+        for child in group.children() {
+            match child {
+                Node::Group(child) => self.draw_group(child, transform, opacity, recolor),
+                Node::Path(path) if path.is_visible() => {
+                    self.draw_path(path, transform, opacity, recolor);
+                }
+                _ => {}
+            }
+        }
+
+        if clip_path.is_some() {
+            self.content.push_str("Q\n");
+        }
+    }
+
+    /// Adds the shapes of a clip path to the current path.
+    fn add_clip(&mut self, group: &Group, transform: Transform) {
+        for child in group.children() {
+            match child {
+                Node::Group(child) => self.add_clip(child, transform.pre_concat(child.transform())),
+                Node::Path(path) => {
+                    if let Some(data) = path.data().clone().transform(transform) {
+                        self.add_path(&data);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn draw_path(
+        &mut self,
+        path: &Path,
+        transform: Transform,
+        opacity: f32,
+        recolor: &dyn Fn([u8; 3]) -> [u8; 3],
+    ) {
+        let transform = transform.pre_concat(path.abs_transform());
+        if let Some(fill) = path.fill()
+            && let Paint::Color(color) = fill.paint()
+        {
+            self.content.push_str("q\n");
+            self.set_transform(transform);
+            self.set_opacity(opacity * fill.opacity().get());
+            let [red, green, blue] = recolor([color.red, color.green, color.blue]);
+            self.content
+                .push_str(&format!("{} rg\n", color_components(red, green, blue)));
+            self.add_path(path.data());
+            self.content.push_str(match fill.rule() {
+                FillRule::NonZero => "f\nQ\n",
+                FillRule::EvenOdd => "f*\nQ\n",
+            });
+        }
+
+        if let Some(stroke) = path.stroke()
+            && let Paint::Color(color) = stroke.paint()
+        {
+            self.content.push_str("q\n");
+            self.set_transform(transform);
+            self.set_opacity(opacity * stroke.opacity().get());
+            let [red, green, blue] = recolor([color.red, color.green, color.blue]);
+            let cap = match stroke.linecap() {
+                LineCap::Butt => 0,
+                LineCap::Round => 1,
+                LineCap::Square => 2,
+            };
+            let join = match stroke.linejoin() {
+                LineJoin::Miter | LineJoin::MiterClip => 0,
+                LineJoin::Round => 1,
+                LineJoin::Bevel => 2,
+            };
+            self.content.push_str(&format!(
+                "{} RG {} w {cap} J {join} j {} M\n",
+                color_components(red, green, blue),
+                format_decimal(stroke.width().get().into()),
+                format_decimal(stroke.miterlimit().get().into()),
+            ));
+            if let Some(dashes) = stroke.dasharray() {
+                let dashes: Vec<_> = dashes
+                    .iter()
+                    .map(|&dash| format_decimal(dash.into()))
+                    .collect();
+                self.content.push_str(&format!(
+                    "[{}] {} d\n",
+                    dashes.join(" "),
+                    format_decimal(stroke.dashoffset().into())
+                ));
+            }
+            self.add_path(path.data());
+            self.content.push_str("S\nQ\n");
+        }
+    }
+
+    fn set_transform(&mut self, transform: Transform) {
+        if transform.is_identity() {
+            return;
+        }
+
+        let Transform {
+            sx,
+            ky,
+            kx,
+            sy,
+            tx,
+            ty,
+        } = transform;
+        let components = [sx, ky, kx, sy, tx, ty].map(|component| format_decimal(component.into()));
+        self.content
+            .push_str(&format!("{} cm\n", components.join(" ")));
+    }
+
+    fn set_opacity(&mut self, opacity: f32) {
+        let opacity = (opacity.clamp(0.0, 1.0) * 1000.0).round() as u16;
+        if opacity < 1000 {
+            self.opacities.insert(opacity);
+            self.content
+                .push_str(&format!("/{} gs\n", opacity_state_name(opacity)));
+        }
+    }
+
+    fn add_path(&mut self, data: &PathData) {
+        let point = |point: Point| {
+            format!(
+                "{} {}",
+                format_decimal(point.x.into()),
+                format_decimal(point.y.into())
+            )
+        };
+        let mut start = None;
+        let mut current = None;
+        for segment in data.segments() {
+            match segment {
+                PathSegment::MoveTo(to) => {
+                    self.content.push_str(&format!("{} m\n", point(to)));
+                    start = Some(to);
+                    current = Some(to);
+                }
+                PathSegment::LineTo(to) => {
+                    self.content.push_str(&format!("{} l\n", point(to)));
+                    current = Some(to);
+                }
+                // PDF has no quadratic curves, so they are drawn as the
+                // equivalent cubic ones.
+                PathSegment::QuadTo(control, to) => {
+                    let from = current.unwrap_or(control);
+                    let two_thirds = Point::from_xy(2.0 / 3.0, 2.0 / 3.0);
+                    let first = from + (control - from) * two_thirds;
+                    let second = to + (control - to) * two_thirds;
+                    self.content.push_str(&format!(
+                        "{} {} {} c\n",
+                        point(first),
+                        point(second),
+                        point(to)
+                    ));
+                    current = Some(to);
+                }
+                PathSegment::CubicTo(first, second, to) => {
+                    self.content.push_str(&format!(
+                        "{} {} {} c\n",
+                        point(first),
+                        point(second),
+                        point(to)
+                    ));
+                    current = Some(to);
+                }
+                PathSegment::Close => {
+                    self.content.push_str("h\n");
+                    current = start;
+                }
+            }
+        }
+    }
+}
+
+/// Returns the name of the graphics state setting given opacity, in
+/// thousandths.
+pub(crate) fn opacity_state_name(opacity: u16) -> String {
+    format!("A{opacity}")
+}
+
+fn color_components(red: u8, green: u8, blue: u8) -> String {
+    [red, green, blue]
+        .map(|component| format_decimal(f64::from(component) / 255.0))
+        .join(" ")
+}